@@ -1,21 +1,51 @@
 use crate::{
-    diagnostics::Position,
-    syntax::token::{Token, TokenType},
+    diagnostics::{Diagnostic, Position, Span},
+    syntax::{
+        ast,
+        token::{CharWidth, NumericSuffix, StringPart, Token, TokenType},
+    },
 };
 use phf::phf_map;
-use std::str::Chars;
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead},
+    iter::FusedIterator,
+    num::{IntErrorKind, ParseIntError},
+    path::{Path, PathBuf},
+    str::Chars,
+};
 
-pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
+pub static KEYWORDS: phf::Map<&'static str, TokenType<'static>> = phf_map! {
     "module" => TokenType::Module,
     "trait" => TokenType::Trait,
     "type" => TokenType::Type,
     "extend" => TokenType::Extend,
     "function" => TokenType::Function,
     "value" => TokenType::Value,
+    "alias" => TokenType::Alias,
     "use" => TokenType::Use,
     "return" => TokenType::Return,
+    "as" => TokenType::As,
+    "if" => TokenType::If,
+    "else" => TokenType::Else,
+    "match" => TokenType::Match,
+    "while" => TokenType::While,
+    "for" => TokenType::For,
+    "in" => TokenType::In,
+    "loop" => TokenType::Loop,
+    "break" => TokenType::Break,
+    "continue" => TokenType::Continue,
+    "public" => TokenType::Public,
+    "private" => TokenType::Private,
+    "mutable" => TokenType::Mutable,
+    "constant" => TokenType::Constant,
+    "self" => TokenType::LowerSelf,
+    "Self" => TokenType::UpperSelf,
     "true" => TokenType::True,
     "false" => TokenType::False,
+    "null" => TokenType::Null,
     "bit" => TokenType::Bit,
     "bit8" => TokenType::Bit8,
     "bit16" => TokenType::Bit16,
@@ -38,404 +68,9172 @@ pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "char32" => TokenType::Char32,
 };
 
-pub struct Parser {}
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexingError {
+    pub kind: LexingErrorKind,
+    // The start of the offending token.
+    pub position: Position,
+    // Just past the offending token, or as much of it as was skipped.
+    pub end: Position,
+}
 
-#[derive(Debug, PartialEq)]
-pub enum LexingError {
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexingErrorKind {
     MultipleDecimalPoints,
     DecimalParsing,
     BitsParsing,
     IntegerParsing,
     UnknownToken,
-    End,
+    // An unknown escape, or a `\x` escape without exactly two hex digits.
+    // `\x80` through `\xFF` are accepted as U+0080 through U+00FF.
     InvalidEscapeSequence,
     IncompleteCharacter,
+    EmptyCharacter,
+    CharacterTooLong,
     IncompleteString,
+    UnterminatedComment,
+    InvalidDigit,
+    MalformedNumericSeparator,
+    InvalidUnicodeEscape,
+    InvalidTypeWidth,
+    UnexpectedBom,
+    UnknownSuffix(String),
+    UnterminatedInterpolation,
+    InterpolationTooDeep,
+    // A literal too large for the type that holds it, such as `int64` for an
+    // unsuffixed whole number.
+    LiteralOutOfRange {
+        literal: String,
+        target: &'static str,
+    },
+    // A type suffix that can't hold the literal, like `1.5int8`.
+    MismatchedSuffix,
+}
+
+impl fmt::Display for LexingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            LexingErrorKind::MultipleDecimalPoints => f.write_str("number with more than one '.'"),
+            LexingErrorKind::DecimalParsing => f.write_str("invalid decimal number"),
+            LexingErrorKind::BitsParsing => f.write_str("invalid bits literal"),
+            LexingErrorKind::IntegerParsing => f.write_str("invalid integer"),
+            LexingErrorKind::UnknownToken => f.write_str("unknown token"),
+            LexingErrorKind::InvalidEscapeSequence => f.write_str("invalid escape sequence"),
+            LexingErrorKind::IncompleteCharacter => f.write_str("unterminated character"),
+            LexingErrorKind::EmptyCharacter => f.write_str("empty character"),
+            LexingErrorKind::CharacterTooLong => f.write_str("more than one character"),
+            LexingErrorKind::IncompleteString => f.write_str("unterminated string"),
+            LexingErrorKind::UnterminatedComment => f.write_str("unterminated comment"),
+            LexingErrorKind::InvalidDigit => f.write_str("invalid digit"),
+            LexingErrorKind::MalformedNumericSeparator => f.write_str("misplaced '_' in number"),
+            LexingErrorKind::InvalidUnicodeEscape => f.write_str("invalid unicode escape"),
+            LexingErrorKind::InvalidTypeWidth => f.write_str("invalid type width"),
+            LexingErrorKind::UnexpectedBom => f.write_str("byte order mark after the start"),
+            LexingErrorKind::UnknownSuffix(suffix) => write!(f, "unknown suffix `{suffix}`"),
+            LexingErrorKind::UnterminatedInterpolation => {
+                f.write_str("unterminated string interpolation")
+            }
+            LexingErrorKind::InterpolationTooDeep => {
+                f.write_str("string interpolation nested too deep")
+            }
+            LexingErrorKind::LiteralOutOfRange { literal, target } => {
+                write!(f, "`{literal}` is out of range for {target}")
+            }
+            LexingErrorKind::MismatchedSuffix => f.write_str("suffix can't hold the literal"),
+        }
+    }
+}
+
+impl From<LexingError> for Diagnostic {
+    fn from(error: LexingError) -> Self {
+        Diagnostic {
+            message: error.to_string(),
+            span: Span {
+                start: error.position.clone(),
+                end: error.end.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LexOutput<'a> {
+    // Every token, ending with `EndOfFile`.
+    pub tokens: Vec<Token<'a>>,
+    pub errors: Vec<LexingError>,
+}
+
+pub fn lex_all(source: &str) -> LexOutput<'_> {
+    Lexer::from_str(source).lex_all()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FileLexingError {
+    pub path: PathBuf,
+    pub error: LexingError,
+}
+
+// Reads the whole file at `path` into `source` and lexes it.
+pub fn lex_file<'a>(
+    path: &Path,
+    source: &'a mut String,
+) -> io::Result<(Vec<Token<'a>>, Vec<FileLexingError>)> {
+    *source = std::fs::read_to_string(path)?;
+    let LexOutput { tokens, errors } = lex_all(source);
+    let errors = errors
+        .into_iter()
+        .map(|error| FileLexingError {
+            path: path.to_path_buf(),
+            error,
+        })
+        .collect();
+    Ok((tokens, errors))
+}
+
+// A numeric literal before its suffix is applied. Plain decimal digits are
+// kept wide enough that a `bit` suffix can still claim values above `int64`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Literal {
+    Bits(u64),
+    Integer(i128),
+    Decimal(f64),
+}
+
+// Recognizes the sized types without a keyword of their own, like `bit3`,
+// whose width must be within 1 to 65535.
+fn sized_type(buffer: &str) -> Result<Option<TokenType<'static>>, LexingErrorKind> {
+    let families = [
+        ("bit", TokenType::BitN as fn(u16) -> TokenType<'static>),
+        ("int", TokenType::IntN),
+        ("float", TokenType::FloatN),
+        ("char", TokenType::CharN),
+    ];
+
+    for (prefix, r#type) in families {
+        let Some(width) = buffer.strip_prefix(prefix) else {
+            continue;
+        };
+        if width.is_empty() || !width.bytes().all(|digit| digit.is_ascii_digit()) {
+            continue;
+        }
+
+        return match width.parse::<u16>() {
+            Ok(width) if width > 0 => Ok(Some(r#type(width))),
+            _ => Err(LexingErrorKind::InvalidTypeWidth),
+        };
+    }
+    Ok(None)
+}
+
+// Recognizes the type names a numeric literal can be suffixed with: `bit`,
+// `int` and `float`, each optionally followed by a width.
+fn numeric_suffix(text: &str) -> Result<Option<NumericSuffix>, LexingErrorKind> {
+    let families = [
+        (
+            "bit",
+            NumericSuffix::Bit as fn(Option<u16>) -> NumericSuffix,
+        ),
+        ("int", NumericSuffix::Int),
+        ("float", NumericSuffix::Float),
+    ];
+
+    for (prefix, suffix) in families {
+        let Some(width) = text.strip_prefix(prefix) else {
+            continue;
+        };
+        if width.is_empty() {
+            return Ok(Some(suffix(None)));
+        }
+        if !width.bytes().all(|digit| digit.is_ascii_digit()) {
+            continue;
+        }
+
+        return match width.parse::<u16>() {
+            Ok(width) if width > 0 => Ok(Some(suffix(Some(width)))),
+            _ => Err(LexingErrorKind::InvalidTypeWidth),
+        };
+    }
+    Ok(None)
+}
+
+fn out_of_range(literal: &str, target: &'static str) -> LexingErrorKind {
+    LexingErrorKind::LiteralOutOfRange {
+        literal: literal.to_string(),
+        target,
+    }
+}
+
+// Tells a literal too large for `target` apart from digits that failed to
+// parse for any other reason.
+fn integer_error(
+    error: &ParseIntError,
+    literal: &str,
+    target: &'static str,
+    otherwise: LexingErrorKind,
+) -> LexingErrorKind {
+    match error.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => out_of_range(literal, target),
+        _ => otherwise,
+    }
+}
+
+fn starts_with_suffix(source: &str) -> bool {
+    let end = source
+        .find(|next: char| !next.is_alphanumeric() && next != '_')
+        .unwrap_or(source.len());
+    matches!(numeric_suffix(&source[..end]), Ok(Some(_)))
+}
+
+// Recognizes the prefix of a character literal like `c16'a'`.
+fn char_width(buffer: &str) -> Option<CharWidth> {
+    match buffer {
+        "c8" => Some(CharWidth::Char8),
+        "c16" => Some(CharWidth::Char16),
+        "c32" => Some(CharWidth::Char32),
+        _ => None,
+    }
+}
+
+fn is_punctuator(current: char) -> bool {
+    "'\".,:;=+-*/\\|&!?@#%^~${}()<>[]".contains(current)
+}
+
+// Removes the `_` separators from a numeric literal, requiring every one of
+// them to sit between two digits of the given radix.
+fn strip_separators(buffer: &str, radix: u32) -> Result<Cow<'_, str>, LexingErrorKind> {
+    if !buffer.contains('_') {
+        return Ok(Cow::Borrowed(buffer));
+    }
+
+    let characters: Vec<char> = buffer.chars().collect();
+    for (i, character) in characters.iter().enumerate() {
+        if *character != '_' {
+            continue;
+        }
+
+        let before = i > 0 && characters[i - 1].is_digit(radix);
+        let after = characters
+            .get(i + 1)
+            .is_some_and(|next| next.is_digit(radix));
+        if !before || !after {
+            return Err(LexingErrorKind::MalformedNumericSeparator);
+        }
+    }
+
+    Ok(Cow::Owned(buffer.replace('_', "")))
+}
+
+// Joins the lines of a `"""` string after dropping a blank first and last
+// line and the indentation common to every other non-blank line. Escaped
+// characters never count as indentation or line breaks.
+fn dedent(characters: &[(bool, char)]) -> String {
+    let is_indentation =
+        |&(is_escaped, current): &(bool, char)| !is_escaped && (current == ' ' || current == '\t');
+    let is_blank = |line: &[(bool, char)]| line.iter().all(is_indentation);
+
+    let mut lines: Vec<&[(bool, char)]> = characters.split(|c| *c == (false, '\n')).collect();
+    if lines.len() > 1 && is_blank(lines[0]) {
+        lines.remove(0);
+    }
+    if lines.len() > 1 && is_blank(lines[lines.len() - 1]) {
+        lines.pop();
+    }
+
+    let indentation = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| line.iter().take_while(|c| is_indentation(c)).count())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.iter().skip(indentation).map(|c| c.1).collect())
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
+    source: &'a str,
+    iterator: Chars<'a>,
+    position: Position,
+    offset: usize,
+    // The next character, once `peek_char` has read it.
+    peeked: Option<char>,
+    previous: Position,
+    finished: bool,
+    tab_width: u32,
+    signed_literals: bool,
+    // How many interpolations this lexer is nested in.
+    interpolation_depth: usize,
+    // An error in the code of an interpolation, which is reported where it
+    // is rather than over the whole string.
+    interpolation_error: Option<LexingError>,
+    trace: Option<fn(&TraceEvent)>,
+    // Peeked results, each with the position the lexer was at before it.
+    lookahead: VecDeque<(Position, Result<Token<'a>, LexingError>)>,
+}
+
+// The lexer's state at some point, which `Lexer::rewind` returns it to.
+#[derive(Debug, Clone)]
+pub struct LexerCheckpoint<'a> {
     iterator: Chars<'a>,
     position: Position,
+    offset: usize,
+    peeked: Option<char>,
+    previous: Position,
+    finished: bool,
+    lookahead: VecDeque<(Position, Result<Token<'a>, LexingError>)>,
+}
+
+#[derive(Debug)]
+pub enum TraceEvent<'t> {
+    Token(&'t Token<'t>),
+    Error(&'t LexingError),
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(iterator: Chars<'a>) -> Self {
+    pub fn new(mut iterator: Chars<'a>) -> Self {
+        // Skip the byte order mark some editors put at the start of a file.
+        if iterator.as_str().starts_with('\u{FEFF}') {
+            iterator.next();
+        }
+
+        let mut lexer = Self::resume(iterator.as_str(), Position { row: 1, column: 1 });
+        lexer.skip_shebang();
+        lexer
+    }
+
+    // Skips a `#!` line at the very start of the input, which lets a file be
+    // run as a script.
+    fn skip_shebang(&mut self) {
+        if self.offset == 0 && self.source.starts_with("#!") {
+            while self.increment().is_some_and(|current| current != '\n') {}
+        }
+    }
+
+    // Lexes `source` as if it began at `position` in some larger input.
+    fn resume(source: &'a str, position: Position) -> Self {
         Self {
-            iterator: iterator.clone(),
-            position: Position { row: 1, column: 1 },
+            source,
+            iterator: source.chars(),
+            position: position.clone(),
+            offset: 0,
+            peeked: None,
+            previous: position,
+            finished: false,
+            tab_width: 1,
+            signed_literals: false,
+            interpolation_depth: 0,
+            interpolation_error: None,
+            trace: None,
+            lookahead: VecDeque::new(),
         }
     }
 
+    // `FromStr` can't be implemented since the lexer borrows from `source`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(source: &'a str) -> Self {
+        Self::new(source.chars())
+    }
+
+    // Calls `trace` with every token and error the lexer produces.
+    pub fn set_trace(&mut self, trace: fn(&TraceEvent)) {
+        self.trace = Some(trace);
+    }
+
+    // Makes a tab advance the column to the next multiple of `tab_width`.
+    pub fn with_tab_width(mut self, tab_width: u32) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    // Folds a `+` or `-` into the numeric literal right after it, so `-1` is
+    // one token rather than a sign and a literal.
+    pub fn with_signed_literals(mut self) -> Self {
+        self.signed_literals = true;
+        self
+    }
+
+    // The position just past the last token returned by `next`, regardless of
+    // how far the lexer has peeked.
     pub fn position(&self) -> &Position {
-        &self.position
+        match self.lookahead.front() {
+            Some((position, _)) => position,
+            None => &self.position,
+        }
     }
 
-    fn increment(&mut self) -> Option<char> {
-        let current = self.iterator.next();
-        if let None = current {
-            return None;
+    pub fn checkpoint(&self) -> LexerCheckpoint<'a> {
+        LexerCheckpoint {
+            iterator: self.iterator.clone(),
+            position: self.position.clone(),
+            offset: self.offset,
+            peeked: self.peeked,
+            previous: self.previous.clone(),
+            finished: self.finished,
+            lookahead: self.lookahead.clone(),
+        }
+    }
+
+    pub fn rewind(&mut self, checkpoint: LexerCheckpoint<'a>) {
+        self.iterator = checkpoint.iterator;
+        self.position = checkpoint.position;
+        self.offset = checkpoint.offset;
+        self.peeked = checkpoint.peeked;
+        self.previous = checkpoint.previous;
+        self.finished = checkpoint.finished;
+        self.lookahead = checkpoint.lookahead;
+    }
+
+    pub fn peek(&mut self) -> Option<&Result<Token<'a>, LexingError>> {
+        self.peek_n(0)
+    }
+
+    // Returns the result `n` places ahead of the next one without consuming
+    // anything.
+    pub fn peek_n(&mut self, n: usize) -> Option<&Result<Token<'a>, LexingError>> {
+        while self.lookahead.len() <= n {
+            let position = self.position.clone();
+            let result = self.next_token()?;
+            self.lookahead.push_back((position, result));
+        }
+        self.lookahead.get(n).map(|(_, result)| result)
+    }
+
+    // Lexes the rest of the input, recovering from errors as it goes.
+    pub fn lex_all(&mut self) -> LexOutput<'a> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for result in self.by_ref() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error),
+            }
         }
+        LexOutput { tokens, errors }
+    }
+
+    fn increment(&mut self) -> Option<char> {
+        let current = match self.peeked.take() {
+            Some(current) => current,
+            None => self.iterator.next()?,
+        };
 
-        let current = current.unwrap();
+        self.previous = self.position.clone();
+        self.offset += current.len_utf8();
         if current == '\n' {
             self.position.column = 0;
             self.position.row += 1;
+        } else if current == '\t' {
+            let column = self.position.column.saturating_sub(1);
+            self.position.column = column - column % self.tab_width + self.tab_width;
         }
         self.position.column += 1;
         Some(current)
     }
 
-    fn next_numeric(
-        &mut self,
-        mut current: char,
-        mut r#type: TokenType,
-    ) -> Result<TokenType, LexingError> {
-        let mut buffer = String::new();
+    // Returns the character just read by `increment` so it's read again next.
+    fn push_back(&mut self, current: char) {
+        self.position = self.previous.clone();
+        self.offset -= current.len_utf8();
+        self.iterator = self.source[self.offset..].chars();
+        self.peeked = None;
+    }
 
-        loop {
-            buffer.push(current);
+    fn peek_char(&mut self) -> Option<char> {
+        if self.peeked.is_none() {
+            self.peeked = self.iterator.next();
+        }
+        self.peeked
+    }
+
+    // The input that hasn't been consumed yet, for lookahead past the next
+    // character.
+    fn remaining(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    // Consumes the next character only if it is `expected`.
+    fn next_if(&mut self, expected: char) -> bool {
+        if self.peek_char() != Some(expected) {
+            return false;
+        }
+        self.increment();
+        true
+    }
+
+    // Produces the `EndOfFile` token the first time the input runs out.
+    fn end_of_file(&mut self) -> Option<Result<Token<'a>, LexingError>> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+
+        let mut token = Token::new(self.position.clone());
+        token.r#type = TokenType::EndOfFile;
+        Some(Ok(token))
+    }
 
-            let i = self.increment();
-            if i == None {
+    // Skips the rest of a malformed token so that lexing resumes at the next
+    // whitespace or punctuator.
+    fn recover(&mut self) {
+        while let Some(current) = self.increment() {
+            if current.is_whitespace() || is_punctuator(current) {
+                self.push_back(current);
                 break;
             }
+        }
+    }
 
-            current = i.unwrap();
-            if current == '.' {
-                if r#type == TokenType::DecimalLiteral {
-                    return Err(LexingError::MultipleDecimalPoints);
+    // Skips the rest of a string or character literal with a bad escape in
+    // it, the one that starts at byte `start`, up to its closing quote, or the
+    // end of the line for one that isn't a block string, so what's left of
+    // its contents isn't lexed as tokens.
+    fn recover_literal(&mut self, start: usize) {
+        let quote = self.source[start..self.offset]
+            .chars()
+            .find(|&current| current == '"' || current == '\'')
+            .unwrap_or('"');
+        let block = self.source[start..].starts_with("\"\"\"");
+        let mut quotes = 0;
+        while let Some(current) = self.increment() {
+            match current {
+                '\\' => {
+                    if self.peek_char() != Some('\n') {
+                        self.increment();
+                    }
+                    quotes = 0;
                 }
-                r#type = TokenType::DecimalLiteral;
-            } else if !current.is_numeric() {
-                break;
+                '\n' if !block => {
+                    self.push_back(current);
+                    break;
+                }
+                _ if current == quote => {
+                    quotes += 1;
+                    if !block || quotes == 3 {
+                        break;
+                    }
+                }
+                _ => quotes = 0,
             }
         }
+    }
 
-        match r#type {
-            TokenType::DecimalLiteral => {
-                let decimal = buffer.parse::<f64>();
-                if let Err(_) = decimal {
-                    Err(LexingError::DecimalParsing)
-                } else {
-                    Ok(TokenType::Decimal(decimal.unwrap()))
-                }
+    // Reads a numeric literal starting with `current`, which is either its
+    // first digit or, with signed literals on, its sign.
+    fn next_numeric(&mut self, current: char) -> Result<TokenType<'a>, LexingErrorKind> {
+        let start = self.offset - current.len_utf8();
+        if current == '0' {
+            let radix = match self.peek_char() {
+                Some('x' | 'X') => Some(16),
+                Some('b' | 'B') => Some(2),
+                Some('o' | 'O') => Some(8),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                self.increment();
+                return self.next_radix_numeric(radix, start);
             }
-            TokenType::BitsLiteral => {
-                let bits = buffer.parse::<u64>();
-                if let Err(_) = bits {
-                    Err(LexingError::BitsParsing)
-                } else {
-                    Ok(TokenType::Bits(bits.unwrap()))
+        }
+
+        let mut decimal = false;
+        let mut exponent = false;
+
+        while let Some(current) = self.increment() {
+            if current == '.' && !exponent {
+                // A point not followed by a digit belongs to the next token,
+                // as in `1..5` or `1.field`.
+                if !self.peek_char().is_some_and(|next| next.is_ascii_digit()) {
+                    self.push_back(current);
+                    break;
                 }
-            }
-            TokenType::IntegerLiteral => {
-                let integer = buffer.parse::<i64>();
-                if let Err(_) = integer {
-                    Err(LexingError::IntegerParsing)
-                } else {
-                    Ok(TokenType::Integer(integer.unwrap()))
+                if decimal {
+                    return Err(LexingErrorKind::MultipleDecimalPoints);
+                }
+                decimal = true;
+            } else if matches!(current, 'e' | 'E') && !exponent {
+                exponent = true;
+                decimal = true;
+
+                if let Some('+' | '-') = self.peek_char() {
+                    self.increment();
+                }
+
+                // The exponent needs at least one digit.
+                match self.increment() {
+                    Some(digit) if digit.is_ascii_digit() => {}
+                    _ => return Err(LexingErrorKind::DecimalParsing),
                 }
+            } else if !current.is_numeric() && current != '_' {
+                self.push_back(current);
+                break;
+            }
+        }
+
+        let literal = &self.source[start..self.offset];
+        let buffer = strip_separators(literal, 10)?;
+        let literal = if decimal {
+            match buffer.parse::<f64>() {
+                // Too many digits for a `float64` parse to infinity.
+                Ok(value) if value.is_infinite() => return Err(out_of_range(literal, "float64")),
+                Ok(value) => Literal::Decimal(value),
+                Err(_) => return Err(LexingErrorKind::DecimalParsing),
+            }
+        } else {
+            Literal::Integer(buffer.parse::<i128>().map_err(|error| {
+                integer_error(&error, literal, "int64", LexingErrorKind::IntegerParsing)
+            })?)
+        };
+        self.next_suffix(literal, start)
+    }
+
+    // Converts a decimal literal that starts at `start` to an `int64`. With
+    // signs left to the parser, `9223372036854775808` is let through as
+    // `i64::MIN` for it to check once it knows whether it's negated.
+    fn int64(&self, value: i128, start: usize) -> Result<i64, LexingErrorKind> {
+        if !self.signed_literals && value == 1 << 63 {
+            return Ok(i64::MIN);
+        }
+        i64::try_from(value).map_err(|_| out_of_range(&self.source[start..self.offset], "int64"))
+    }
+
+    // Reads the digits after a `0x`, `0b` or `0o` prefix, where `literal_start`
+    // is the offset of the literal's `0`.
+    fn next_radix_numeric(
+        &mut self,
+        radix: u32,
+        literal_start: usize,
+    ) -> Result<TokenType<'a>, LexingErrorKind> {
+        let start = self.offset;
+        while let Some(next) = self.peek_char() {
+            // Hexadecimal digits run straight into a suffix like `bit8`.
+            if radix == 16 && starts_with_suffix(self.remaining()) {
+                break;
+            } else if next.is_ascii_digit() && !next.is_digit(radix) {
+                self.increment();
+                return Err(LexingErrorKind::InvalidDigit);
+            } else if !next.is_digit(radix) && next != '_' {
+                break;
             }
-            _ => unreachable!(),
+            self.increment();
         }
+
+        let buffer = strip_separators(&self.source[start..self.offset], radix)?;
+        let value = u64::from_str_radix(&buffer, radix).map_err(|error| {
+            let literal = &self.source[literal_start..self.offset];
+            integer_error(&error, literal, "bit64", LexingErrorKind::BitsParsing)
+        })?;
+        self.next_suffix(Literal::Bits(value), literal_start)
     }
 
-    fn next_character(&mut self) -> Result<(bool, char), LexingError> {
-        let current = self.increment();
-        if let None = current {
-            return Err(LexingError::End);
+    // Reads the type name written straight after a numeric literal, if any,
+    // and converts the literal, which starts at `start`, to it.
+    fn next_suffix(
+        &mut self,
+        literal: Literal,
+        start: usize,
+    ) -> Result<TokenType<'a>, LexingErrorKind> {
+        if !self.peek_char().is_some_and(char::is_alphabetic) {
+            return Ok(match literal {
+                Literal::Bits(value) => TokenType::Bits {
+                    value,
+                    suffix: None,
+                },
+                Literal::Integer(value) => TokenType::Integer {
+                    value: self.int64(value, start)?,
+                    suffix: None,
+                },
+                Literal::Decimal(value) => TokenType::Decimal {
+                    value,
+                    suffix: None,
+                },
+            });
         }
-        let mut current = current.unwrap();
 
-        if current != '\\' {
-            return Ok((false, current));
+        let suffix_start = self.offset;
+        while self
+            .peek_char()
+            .is_some_and(|next| next.is_alphanumeric() || next == '_')
+        {
+            self.increment();
         }
+        let text = &self.source[suffix_start..self.offset];
+        let suffix = numeric_suffix(text)?
+            .ok_or_else(|| LexingErrorKind::UnknownSuffix(text.to_string()))?;
+
+        let literal = match (literal, suffix) {
+            (Literal::Decimal(_), NumericSuffix::Bit(_) | NumericSuffix::Int(_)) => {
+                return Err(LexingErrorKind::MismatchedSuffix)
+            }
+            (Literal::Decimal(value), NumericSuffix::Float(_)) => TokenType::Decimal {
+                value,
+                suffix: Some(suffix),
+            },
+            (Literal::Bits(value), NumericSuffix::Bit(_)) => TokenType::Bits {
+                value,
+                suffix: Some(suffix),
+            },
+            (Literal::Bits(value), NumericSuffix::Int(_)) => TokenType::Integer {
+                value: i64::try_from(value)
+                    .map_err(|_| out_of_range(&self.source[start..self.offset], "int64"))?,
+                suffix: Some(suffix),
+            },
+            (Literal::Integer(value), NumericSuffix::Bit(_)) => TokenType::Bits {
+                value: u64::try_from(value)
+                    .map_err(|_| out_of_range(&self.source[start..self.offset], "bit64"))?,
+                suffix: Some(suffix),
+            },
+            (Literal::Integer(value), NumericSuffix::Int(_)) => TokenType::Integer {
+                value: self.int64(value, start)?,
+                suffix: Some(suffix),
+            },
+            (Literal::Bits(value), NumericSuffix::Float(_)) => TokenType::Decimal {
+                value: value as f64,
+                suffix: Some(suffix),
+            },
+            (Literal::Integer(value), NumericSuffix::Float(_)) => TokenType::Decimal {
+                value: value as f64,
+                suffix: Some(suffix),
+            },
+        };
+        Ok(literal)
+    }
 
-        let result = self.increment();
-        if result == None {
-            return Err(LexingError::End);
+    // Returns `None` at the end of the input.
+    fn next_character(&mut self) -> Result<Option<(bool, char)>, LexingErrorKind> {
+        let Some(mut current) = self.increment() else {
+            return Ok(None);
+        };
+
+        if current != '\\' {
+            return Ok(Some((false, current)));
         }
-        current = result.unwrap();
+
+        let Some(result) = self.increment() else {
+            return Ok(None);
+        };
+        current = result;
 
         let result = match current {
             '\\' | '\'' | '\"' => current,
-            _ => return Err(LexingError::InvalidEscapeSequence),
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'u' => self.next_unicode_escape()?,
+            'x' => self.next_byte_escape()?,
+            _ => return Err(LexingErrorKind::InvalidEscapeSequence),
         };
 
-        Ok((true, result))
+        Ok(Some((true, result)))
     }
-}
-
-impl<'a> Iterator for Lexer<'a> {
-    type Item = Result<Token, LexingError>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let current = self.increment();
-        if let None = current {
-            return None;
+    fn next_byte_escape(&mut self) -> Result<char, LexingErrorKind> {
+        let mut value = 0;
+        for _ in 0..2 {
+            let digit = self
+                .peek_char()
+                .and_then(|digit| digit.to_digit(16))
+                .ok_or(LexingErrorKind::InvalidEscapeSequence)?;
+            self.increment();
+            value = value * 16 + digit;
         }
 
-        let mut current = current.unwrap();
+        Ok(char::from(value as u8))
+    }
+
+    fn next_unicode_escape(&mut self) -> Result<char, LexingErrorKind> {
+        if !self.next_if('{') {
+            return Err(LexingErrorKind::InvalidUnicodeEscape);
+        }
 
-        // Skip whitespace.
+        // Nothing past the escape is consumed, so that a closing quote is
+        // left for recovery to find.
+        let mut buffer = String::new();
         loop {
-            if !current.is_whitespace() {
-                break;
+            match self.peek_char() {
+                Some('}') => {
+                    self.increment();
+                    break;
+                }
+                Some(digit) if digit.is_ascii_hexdigit() && buffer.len() < 6 => {
+                    self.increment();
+                    buffer.push(digit);
+                }
+                _ => return Err(LexingErrorKind::InvalidUnicodeEscape),
             }
+        }
 
-            let i = self.increment();
-            if let None = i {
-                return None;
-            }
+        u32::from_str_radix(&buffer, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(LexingErrorKind::InvalidUnicodeEscape)
+    }
 
-            current = i.unwrap();
+    // Lexes the tokens of a `\(...)` interpolation up to its closing
+    // parenthesis, which has to be balanced with any inside it.
+    fn next_interpolation(&mut self) -> Result<Vec<Token<'a>>, LexingErrorKind> {
+        if self.interpolation_depth == MAX_INTERPOLATION_DEPTH {
+            return Err(LexingErrorKind::InterpolationTooDeep);
         }
 
-        let mut token = Token::new(Position {
-            row: self.position.row,
-            column: self.position.column - 1,
-        });
+        let mut lexer = Lexer::resume(self.remaining(), self.position.clone());
+        lexer.tab_width = self.tab_width;
+        lexer.signed_literals = self.signed_literals;
+        lexer.interpolation_depth = self.interpolation_depth + 1;
 
-        // Match the start symbol.
-        token.r#type = match current {
-            '\'' => match self.next_character() {
-                Ok((is_escaped, mut ok)) => {
-                    if ok == '\'' && !is_escaped {
-                        ok = '\0';
-                    } else {
-                        let current = self.increment();
-                        if let None = current {
-                            return Some(Err(LexingError::IncompleteCharacter));
-                        }
+        // However it ends, this lexer carries on from where the one for the
+        // interpolation stopped.
+        let mut tokens = Vec::new();
+        let mut depth = 1;
+        let result = loop {
+            let token = match lexer.next_token() {
+                Some(Ok(token)) => token,
+                Some(Err(error)) => {
+                    let kind = error.kind.clone();
+                    self.interpolation_error = Some(error);
+                    break Err(kind);
+                }
+                None => break Err(LexingErrorKind::UnterminatedInterpolation),
+            };
+            match token.r#type {
+                TokenType::LeftParenthesis => depth += 1,
+                TokenType::RightParenthesis => depth -= 1,
+                TokenType::EndOfFile => break Err(LexingErrorKind::UnterminatedInterpolation),
+                _ => {}
+            }
+            if depth == 0 {
+                break Ok(tokens);
+            }
+            tokens.push(token);
+        };
 
-                        let current = current.unwrap();
-                        println!("{:?} {:?}", ok, current);
+        self.offset += lexer.offset;
+        self.position = lexer.position;
+        self.previous = lexer.previous;
+        self.iterator = self.source[self.offset..].chars();
+        self.peeked = None;
+        result
+    }
 
-                        if current != '\'' {
-                            return Some(Err(LexingError::IncompleteCharacter));
-                        }
-                    }
-                    TokenType::Character(ok)
+    // Reads the rest of a character literal after its opening quote.
+    fn next_character_literal(&mut self) -> Result<char, LexingErrorKind> {
+        let (is_escaped, value) = self
+            .next_character()?
+            .ok_or(LexingErrorKind::IncompleteCharacter)?;
+        if value == '\'' && !is_escaped {
+            return Err(LexingErrorKind::EmptyCharacter);
+        }
+        if value == '\n' && !is_escaped {
+            self.push_back(value);
+            return Err(LexingErrorKind::IncompleteCharacter);
+        }
+
+        // Look for the closing quote on the rest of the line to tell `'ab'`
+        // apart from a quote that was never closed.
+        let mut length = 1;
+        loop {
+            match self.next_character()? {
+                Some((false, '\'')) if length == 1 => return Ok(value),
+                Some((false, '\'')) => return Err(LexingErrorKind::CharacterTooLong),
+                Some((false, '\n')) => {
+                    self.push_back('\n');
+                    return Err(LexingErrorKind::IncompleteCharacter);
                 }
-                Err(e) => {
-                    if e != LexingError::End {
-                        return Some(Err(e));
+                Some(_) => length += 1,
+                None => return Err(LexingErrorKind::IncompleteCharacter),
+            }
+        }
+    }
+
+    // Reads the rest of a literal like `c16'a'` after its opening quote,
+    // checking that the character fits in the width.
+    fn next_sized_character(
+        &mut self,
+        width: CharWidth,
+        start: usize,
+    ) -> Result<TokenType<'a>, LexingErrorKind> {
+        let value = self.next_character_literal()?;
+        let (limit, target) = match width {
+            CharWidth::Char8 => ('\u{FF}', "char8"),
+            CharWidth::Char16 => ('\u{FFFF}', "char16"),
+            CharWidth::Char32 => (char::MAX, "char32"),
+        };
+        if value > limit {
+            return Err(out_of_range(&self.source[start..self.offset], target));
+        }
+        Ok(TokenType::Character {
+            value,
+            width: Some(width),
+        })
+    }
+
+    fn next_block_string(&mut self) -> Result<String, LexingErrorKind> {
+        let mut characters = Vec::new();
+        loop {
+            let current = self
+                .next_character()?
+                .ok_or(LexingErrorKind::IncompleteString)?;
+            characters.push(current);
+
+            if characters.ends_with(&[(false, '"'); 3]) {
+                characters.truncate(characters.len() - 3);
+                return Ok(dedent(&characters));
+            }
+        }
+    }
+
+    fn next_type(&mut self, current: char) -> Result<TokenType<'a>, LexingErrorKind> {
+        let start = self.offset - current.len_utf8();
+        let r#type = match current {
+            '\'' => TokenType::Character {
+                value: self.next_character_literal()?,
+                width: None,
+            },
+            'r' if self.peek_char() == Some('"') => {
+                self.increment();
+
+                loop {
+                    match self.increment() {
+                        Some('"') => break,
+                        Some(_) => {}
+                        None => return Err(LexingErrorKind::IncompleteString),
                     }
-                    return Some(Err(LexingError::IncompleteCharacter));
                 }
-            },
+                TokenType::String(Cow::Borrowed(&self.source[start + 2..self.offset - 1]))
+            }
+            '"' if self.remaining().starts_with("\"\"") => {
+                self.increment();
+                self.increment();
+                TokenType::String(Cow::Owned(self.next_block_string()?))
+            }
             '"' => {
-                let mut buffer = String::new();
-                loop {
-                    let current = self.next_character();
-                    if let Err(e) = current {
-                        if e != LexingError::End {
-                            return Some(Err(e));
+                // The contents are borrowed from the source until an escape
+                // forces them to be copied.
+                let mut parts = Vec::new();
+                let mut literal_start = start + 1;
+                let mut buffer: Option<String> = None;
+                let literal = loop {
+                    let before = self.offset;
+                    if self.remaining().starts_with("\\(") {
+                        let literal = match buffer.take() {
+                            Some(buffer) => Cow::Owned(buffer),
+                            None => Cow::Borrowed(&self.source[literal_start..before]),
+                        };
+                        if !literal.is_empty() {
+                            parts.push(StringPart::Literal(literal));
                         }
-                        return Some(Err(LexingError::IncompleteString));
+
+                        self.increment();
+                        self.increment();
+                        parts.push(StringPart::Tokens(self.next_interpolation()?));
+                        literal_start = self.offset;
+                        continue;
                     }
 
-                    let (is_escaped, current) = current.unwrap();
+                    let (is_escaped, current) = self
+                        .next_character()?
+                        .ok_or(LexingErrorKind::IncompleteString)?;
                     if current == '"' && !is_escaped {
-                        break;
+                        break match buffer {
+                            Some(buffer) => Cow::Owned(buffer),
+                            None => Cow::Borrowed(&self.source[literal_start..before]),
+                        };
                     }
-                    buffer.push(current);
-                }
 
-                if buffer.is_empty() {
-                    buffer.push(0 as char);
+                    if let Some(buffer) = &mut buffer {
+                        buffer.push(current);
+                    } else if is_escaped {
+                        let mut copy = self.source[literal_start..before].to_string();
+                        copy.push(current);
+                        buffer = Some(copy);
+                    }
+                };
+
+                if parts.is_empty() {
+                    TokenType::String(literal)
+                } else {
+                    if !literal.is_empty() {
+                        parts.push(StringPart::Literal(literal));
+                    }
+                    TokenType::InterpolatedString(parts)
+                }
+            }
+            '.' => {
+                if !self.next_if('.') {
+                    TokenType::FullStop
+                } else if self.next_if('=') {
+                    TokenType::DoubleFullStopEquals
+                } else {
+                    TokenType::DoubleFullStop
                 }
-                TokenType::String(buffer)
             }
-            '.' => TokenType::FullStop,
             ',' => TokenType::Comma,
-            ':' => TokenType::Colon,
-            ';' => TokenType::Semicolon,
-            '=' => TokenType::EqualsSign,
-            '+' => {
-                if let Some(next) = self.iterator.clone().peekable().peek() {
-                    if next.is_numeric() {
-                        let result = self.next_numeric(current, TokenType::IntegerLiteral);
-                        if let Err(e) = result {
-                            return Some(Err(e));
-                        }
-                        result.unwrap()
-                    } else {
-                        TokenType::PlusSign
-                    }
+            ':' => {
+                if self.next_if(':') {
+                    TokenType::DoubleColon
+                } else {
+                    TokenType::Colon
+                }
+            }
+            ';' => TokenType::Semicolon,
+            '=' => {
+                if self.next_if('=') {
+                    TokenType::DoubleEquals
+                } else if self.next_if('>') {
+                    TokenType::RightwardsDoubleArrow
+                } else {
+                    TokenType::EqualsSign
+                }
+            }
+            '+' => {
+                if self.next_if('=') {
+                    TokenType::PlusEquals
+                } else if self.signed_literals && self.peek_char().is_some_and(char::is_numeric) {
+                    self.next_numeric(current)?
                 } else {
                     TokenType::PlusSign
                 }
             }
             '-' => {
-                if let Some(next) = self.iterator.clone().peekable().peek() {
-                    if *next == '>' {
-                        self.increment();
-                        TokenType::RightwardsArrow
-                    } else if next.is_numeric() {
-                        let result = self.next_numeric(current, TokenType::IntegerLiteral);
-                        if let Err(e) = result {
-                            return Some(Err(e));
-                        }
-                        result.unwrap()
-                    } else {
-                        TokenType::MinuxSign
-                    }
+                if self.next_if('>') {
+                    TokenType::RightwardsArrow
+                } else if self.next_if('=') {
+                    TokenType::MinusEquals
+                } else if self.signed_literals && self.peek_char().is_some_and(char::is_numeric) {
+                    self.next_numeric(current)?
                 } else {
                     TokenType::MinuxSign
                 }
             }
-            '*' => TokenType::Asterisk,
-            '/' => TokenType::Solidus,
+            '*' => {
+                if self.next_if('=') {
+                    TokenType::AsteriskEquals
+                } else {
+                    TokenType::Asterisk
+                }
+            }
+            '/' => {
+                if self.next_if('=') {
+                    TokenType::SolidusEquals
+                } else {
+                    TokenType::Solidus
+                }
+            }
             '\\' => TokenType::ReverseSolidus,
-            '|' => TokenType::VerticalLine,
-            '!' => TokenType::ExclamationMark,
+            '|' => {
+                if self.next_if('|') {
+                    TokenType::DoubleVerticalLine
+                } else {
+                    TokenType::VerticalLine
+                }
+            }
+            '&' => {
+                if self.next_if('&') {
+                    TokenType::DoubleAmpersand
+                } else {
+                    TokenType::Ampersand
+                }
+            }
+            '!' => {
+                if self.next_if('=') {
+                    TokenType::NotEquals
+                } else {
+                    TokenType::ExclamationMark
+                }
+            }
             '?' => TokenType::QuestionMark,
             '@' => TokenType::ComercialAt,
+            '#' if self.next_if('#') => {
+                let line = self.remaining();
+                let length = line.find('\n').unwrap_or(line.len());
+                while self.offset < start + 2 + length {
+                    self.increment();
+                }
+                let text = line[..length].trim_end_matches('\r').trim_start();
+                TokenType::DocComment(Cow::Borrowed(text))
+            }
             '#' => TokenType::NumberSign,
+            '%' => {
+                if self.next_if('=') {
+                    TokenType::PercentEquals
+                } else {
+                    TokenType::PercentSign
+                }
+            }
+            '^' => {
+                if self.next_if('=') {
+                    TokenType::CircumflexEquals
+                } else {
+                    TokenType::CircumflexAccent
+                }
+            }
+            '~' => TokenType::Tilde,
+            '$' => TokenType::DollarSign,
+            '\u{FEFF}' => return Err(LexingErrorKind::UnexpectedBom),
             '{' => TokenType::LeftCurlyBracket,
             '}' => TokenType::RightCurlyBracket,
             '(' => TokenType::LeftParenthesis,
             ')' => TokenType::RightParenthesis,
-            '<' => TokenType::LeftAngleBracket,
-            '>' => TokenType::RightAngleBracket,
+            '<' => {
+                if self.next_if('=') {
+                    TokenType::LessThanOrEqual
+                } else if self.next_if('<') {
+                    TokenType::DoubleLeftAngle
+                } else {
+                    TokenType::LeftAngleBracket
+                }
+            }
+            '>' => {
+                if self.next_if('=') {
+                    TokenType::GreaterThanOrEqual
+                } else if self.next_if('>') {
+                    TokenType::DoubleRightAngle
+                } else {
+                    TokenType::RightAngleBracket
+                }
+            }
             '[' => TokenType::LeftSquareBracket,
             ']' => TokenType::RightSquareBracket,
             _ => {
                 if current.is_numeric() {
-                    let result = self.next_numeric(current, TokenType::BitsLiteral);
-                    if let Err(e) = result {
-                        return Some(Err(e));
-                    }
-                    result.unwrap()
-                } else if current.is_alphabetic() {
-                    let mut buffer = String::new();
-
-                    loop {
-                        buffer.push(current);
-
-                        let i = self.increment();
-                        if i == None {
-                            break;
-                        }
-
-                        current = i.unwrap();
+                    self.next_numeric(current)?
+                } else if current.is_alphabetic() || current == '_' {
+                    while let Some(current) = self.increment() {
                         if !current.is_alphabetic() && current != '_' && !current.is_numeric() {
+                            self.push_back(current);
                             break;
                         }
                     }
 
-                    if let Some(keyword) = KEYWORDS.get(buffer.as_str()) {
+                    let buffer = &self.source[start..self.offset];
+                    if buffer == "_" {
+                        TokenType::Underscore
+                    } else if let Some(width) =
+                        char_width(buffer).filter(|_| self.remaining().starts_with('\''))
+                    {
+                        self.increment();
+                        self.next_sized_character(width, start)?
+                    } else if let Some(keyword) = KEYWORDS.get(buffer) {
                         match keyword {
                             TokenType::True => TokenType::Boolean(true),
                             TokenType::False => TokenType::Boolean(false),
                             _ => keyword.clone(),
                         }
+                    } else if let Some(r#type) = sized_type(buffer)? {
+                        r#type
+                    } else if self.remaining().starts_with('!')
+                        && !self.remaining()[1..].starts_with('=')
+                    {
+                        self.increment();
+                        TokenType::MacroIdentifier(Cow::Borrowed(buffer))
                     } else {
-                        TokenType::Identifier(buffer)
+                        TokenType::Identifier(Cow::Borrowed(buffer))
                     }
                 } else {
-                    return Some(Err(LexingError::UnknownToken));
+                    return Err(LexingErrorKind::UnknownToken);
+                }
+            }
+        };
+
+        Ok(r#type)
+    }
+
+    fn skip_block_comment(&mut self) -> Result<(), LexingErrorKind> {
+        let mut depth = 1;
+        while depth > 0 {
+            let current = self
+                .increment()
+                .ok_or(LexingErrorKind::UnterminatedComment)?;
+
+            let next = self.peek_char();
+            if current == '#' && next == Some('[') {
+                self.increment();
+                depth += 1;
+            } else if current == ']' && next == Some('#') {
+                self.increment();
+                depth -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token<'a>, LexingError>> {
+        // Skip whitespace and block comments, remembering where the character
+        // being looked at starts.
+        let (current, position, start) = loop {
+            let position = self.position.clone();
+            let start = self.offset;
+            let Some(current) = self.increment() else {
+                return self.end_of_file();
+            };
+
+            if current == '#' && self.next_if('[') {
+                if let Err(kind) = self.skip_block_comment() {
+                    let end = self.position.clone();
+                    return Some(Err(LexingError {
+                        kind,
+                        position,
+                        end,
+                    }));
+                }
+            } else if !current.is_whitespace() {
+                break (current, position, start);
+            }
+        };
+
+        let r#type = match self.next_type(current) {
+            Ok(ok) => ok,
+            Err(kind) => {
+                // An error in an interpolation is reported at its own span,
+                // and the rest of the string skipped like after a bad escape.
+                if let Some(error) = self.interpolation_error.take() {
+                    self.recover_literal(start);
+                    return Some(Err(error));
+                }
+                match kind {
+                    LexingErrorKind::UnknownToken => {}
+                    LexingErrorKind::InvalidEscapeSequence
+                    | LexingErrorKind::InvalidUnicodeEscape => self.recover_literal(start),
+                    _ => self.recover(),
                 }
+                let end = self.position.clone();
+                return Some(Err(LexingError {
+                    kind,
+                    position,
+                    end,
+                }));
             }
         };
 
+        let mut token = Token::new(position);
+        token.r#type = r#type;
+        token.span.end = self.position.clone();
+        token.lexeme = Cow::Borrowed(&self.source[start..self.offset]);
         Some(Ok(token))
     }
 }
 
-#[test]
-pub fn test_lexer() {
-    use std::{fs::File, io::Read};
-    let mut file = File::open("/home/k/projects/xic/tests/lexing_test_file.xi").unwrap();
-    let mut buffer = String::new();
-    file.read_to_string(&mut buffer).unwrap();
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexingError>;
 
-    let tokens = vec![
-        // TokenType::Bits(u64),
-        // TokenType::Integer(i64),
-        // TokenType::Decimal(f64),
-        // TokenType::Boolean(bool),
-        TokenType::Integer(21),
-        TokenType::Integer(-21),
-        TokenType::Decimal(21.21),
-        TokenType::Bits(21),
-        TokenType::Boolean(true),
-        TokenType::Boolean(false),
-        TokenType::Identifier(String::from("C_oolIdentifier32_")),
-        TokenType::Character('\0'),
-        TokenType::String(String::from("\0")),
-        TokenType::Character('\''),
-        TokenType::String(String::from("Hello,\'\" World!")),
-        TokenType::Module,
-        TokenType::Trait,
-        TokenType::Type,
-        TokenType::Extend,
-        TokenType::Function,
-        TokenType::Value,
-        TokenType::Use,
-        TokenType::Return,
-        TokenType::Bit,
-        TokenType::Bit8,
-        TokenType::Bit16,
-        TokenType::Bit32,
-        TokenType::Bit64,
-        TokenType::Int,
-        TokenType::Int8,
-        TokenType::Int16,
-        TokenType::Int32,
-        TokenType::Int64,
-        TokenType::Float,
-        TokenType::Float8,
-        TokenType::Float16,
-        TokenType::Float32,
-        TokenType::Float64,
-        TokenType::Bool,
-        TokenType::Char,
-        TokenType::Char8,
-        TokenType::Char16,
-        TokenType::Char32,
-        TokenType::FullStop,
-        TokenType::Comma,
-        TokenType::Colon,
-        TokenType::Semicolon,
-        TokenType::EqualsSign,
-        TokenType::PlusSign,
-        TokenType::MinuxSign,
-        TokenType::Asterisk,
-        TokenType::Solidus,
-        TokenType::ReverseSolidus,
-        TokenType::VerticalLine,
-        TokenType::ExclamationMark,
-        TokenType::QuestionMark,
-        TokenType::ComercialAt,
-        TokenType::NumberSign,
-        TokenType::RightwardsArrow,
-        TokenType::LeftCurlyBracket,
-        TokenType::RightCurlyBracket,
-        TokenType::LeftParenthesis,
-        TokenType::RightParenthesis,
-        TokenType::LeftAngleBracket,
-        TokenType::RightAngleBracket,
-        TokenType::LeftSquareBracket,
-        TokenType::RightSquareBracket,
-    ];
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.lookahead.pop_front() {
+            Some((_, result)) => result,
+            None => self.next_token()?,
+        };
+        if let Some(trace) = self.trace {
+            trace(&match &result {
+                Ok(token) => TraceEvent::Token(token),
+                Err(error) => TraceEvent::Error(error),
+            });
+        }
+        Some(result)
+    }
+}
 
-    let mut lexer = Lexer::new(buffer.chars());
-    for token in tokens {
-        if let Some(result) = lexer.next() {
-            if let Ok(result) = result {
-                println!("Expected: {:?}, Recieved: {:?}", token, result.r#type);
-                if result.r#type != token {
-                    panic!();
+// Once `EndOfFile` has been produced, every later call returns `None`.
+impl<'a> FusedIterator for Lexer<'a> {}
+
+// The most the lexer reads past the end of a token, in bytes, which is the
+// `!=` after an identifier.
+const LOOKAHEAD: usize = 8;
+
+const CHUNK_SIZE: usize = 8192;
+
+const MAX_INTERPOLATION_DEPTH: usize = 16;
+
+// Lexes the input from a reader a chunk at a time, producing tokens that own
+// their text.
+pub struct StreamingLexer<R: BufRead> {
+    reader: R,
+    chunk_size: usize,
+    // Decoded input that hasn't been lexed yet starts at `consumed`.
+    buffer: String,
+    consumed: usize,
+    // The bytes of a character split across two chunks.
+    partial: Vec<u8>,
+    position: Position,
+    started: bool,
+    exhausted: bool,
+    finished: bool,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> StreamingLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            chunk_size: CHUNK_SIZE,
+            buffer: String::new(),
+            consumed: 0,
+            partial: Vec::new(),
+            position: Position { row: 1, column: 1 },
+            started: false,
+            exhausted: false,
+            finished: false,
+            error: None,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    // The error that cut the input short, if reading failed.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn read_chunk(&mut self) {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+
+        let chunk = match self.reader.fill_buf() {
+            Ok(chunk) => chunk,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => return,
+            Err(error) => {
+                self.error = Some(error);
+                self.exhausted = true;
+                return;
+            }
+        };
+        let length = chunk.len().min(self.chunk_size);
+        self.partial.extend_from_slice(&chunk[..length]);
+        self.reader.consume(length);
+        if length == 0 {
+            self.exhausted = true;
+        }
+
+        // Keep an incomplete character at the end for the next chunk, unless
+        // there won't be one.
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            Err(error) if error.error_len().is_none() && !self.exhausted => error.valid_up_to(),
+            Err(_) => self.partial.len(),
+        };
+        let rest = self.partial.split_off(valid);
+        self.buffer
+            .push_str(&String::from_utf8_lossy(&self.partial));
+        self.partial = rest;
+
+        // Skip a byte order mark once the first character is complete.
+        if self.started || self.buffer.is_empty() {
+            return;
+        }
+        self.started = true;
+        if self.buffer.starts_with('\u{FEFF}') {
+            self.buffer.drain(..'\u{FEFF}'.len_utf8());
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamingLexer<R> {
+    type Item = Result<Token<'static>, LexingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let mut lexer = Lexer::resume(&self.buffer[self.consumed..], self.position.clone());
+            if self.position == (Position { row: 1, column: 1 }) {
+                lexer.skip_shebang();
+            }
+            let result = lexer.next_token();
+
+            // A token too close to the end of the buffer might continue into
+            // the next chunk, so it's lexed again once that has been read.
+            // The lexer ends up past everything it read, even in the code of
+            // an interpolation that failed, so this catches those too.
+            if !self.exhausted && lexer.offset + LOOKAHEAD > lexer.source.len() {
+                self.read_chunk();
+                continue;
+            }
+
+            self.consumed += lexer.offset;
+            self.position = lexer.position;
+            let result = result?;
+            if let Ok(Token {
+                r#type: TokenType::EndOfFile,
+                ..
+            }) = result
+            {
+                self.finished = true;
+            }
+            return Some(result.map(Token::into_owned));
+        }
+    }
+}
+
+impl<R: BufRead> FusedIterator for StreamingLexer<R> {}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    // The token the parser couldn't accept.
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    // Tokens that could have come next but didn't, along with where the
+    // parser was, like "in use declaration".
+    Expected {
+        expected: Vec<TokenDescription>,
+        found: TokenDescription,
+        context: &'static str,
+    },
+    ExpectedIdentifier,
+    // A keyword where a name was expected, as in `module type;`.
+    KeywordAsIdentifier(&'static str),
+    // `use core::{};`
+    EmptyUseGroup,
+    ExpectedType,
+    ExpectedExpression,
+    // A separator with nothing after it, as in `(Monday | Tuesday |)`.
+    TrailingSeparator,
+    // Different composite types in one pair of parentheses, like
+    // `(int32 + int64 | bit8)`.
+    AmbiguousComposite,
+    // An alternative without a type alongside ones with types, like the `B`
+    // in `(A: int | B)`.
+    MissingPayload,
+    DuplicateVariant(String),
+    // An item somewhere it can't be declared, like a `type` in an extend
+    // block, along with where that was.
+    ItemNotAllowed(&'static str),
+    // `value x;`, which gives no way to tell the value's type.
+    MissingTypeAndInitializer,
+    // `a < b < c`
+    ChainedComparison,
+    // `a..b..c`
+    ChainedRange,
+    // A bracket that's never closed, reported where it was opened.
+    UnclosedDelimiter,
+    // Something other than plain digits after the `.` of a tuple field, as
+    // in `pair.1e3`.
+    InvalidTupleIndex,
+    // Assigning to something that isn't a name, field or dereference, as in
+    // `1 + 2 = x;`.
+    InvalidAssignmentTarget,
+    // A `self` parameter after the first one.
+    MisplacedReceiver,
+    // Attributes with no item after them to apply to.
+    DanglingAttribute,
+    // Something at the top of a module that doesn't start an item.
+    ExpectedItem,
+    ExpectedPattern,
+    // A field or parameter without a default after one with it, as in
+    // `function f(x: int = 1, y: int)`.
+    RequiredAfterDefault,
+    // `public public function f();`
+    DuplicateVisibility,
+    // A visibility on something that can't have one, along with what that
+    // was, like "use declaration".
+    VisibilityNotAllowed(&'static str),
+    // A number that doesn't fit once its sign is applied, as in
+    // `9223372036854775808`.
+    LiteralOutOfRange {
+        literal: String,
+        target: &'static str,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::Expected {
+                expected,
+                found,
+                context,
+            } => {
+                f.write_str("expected ")?;
+                for (i, description) in expected.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        _ if i == expected.len() - 1 => f.write_str(" or ")?,
+                        _ => f.write_str(", ")?,
+                    }
+                    write!(f, "{description}")?;
                 }
-            } else {
-                println!("{:?} {:?}", lexer.position(), result);
-                panic!();
+                write!(f, " {context}, found {found}")
             }
-        } else {
-            break;
+            ParseErrorKind::ExpectedIdentifier => f.write_str("expected identifier"),
+            ParseErrorKind::KeywordAsIdentifier(keyword) => {
+                write!(f, "expected identifier, found keyword '{keyword}'")
+            }
+            ParseErrorKind::EmptyUseGroup => f.write_str("empty use group"),
+            ParseErrorKind::ExpectedType => f.write_str("expected type"),
+            ParseErrorKind::ExpectedExpression => f.write_str("expected expression"),
+            ParseErrorKind::TrailingSeparator => f.write_str("trailing separator"),
+            ParseErrorKind::AmbiguousComposite => {
+                f.write_str("different kinds of composite type in one pair of parentheses")
+            }
+            ParseErrorKind::MissingPayload => {
+                f.write_str("alternative without a type among ones with types")
+            }
+            ParseErrorKind::DuplicateVariant(name) => write!(f, "duplicate variant `{name}`"),
+            ParseErrorKind::ItemNotAllowed(context) => write!(f, "item not allowed in {context}"),
+            ParseErrorKind::MissingTypeAndInitializer => {
+                f.write_str("value needs a type or an initializer")
+            }
+            ParseErrorKind::ChainedComparison => f.write_str("comparisons can't be chained"),
+            ParseErrorKind::ChainedRange => f.write_str("ranges can't be chained"),
+            ParseErrorKind::UnclosedDelimiter => f.write_str("unclosed delimiter"),
+            ParseErrorKind::InvalidTupleIndex => f.write_str("invalid tuple index"),
+            ParseErrorKind::InvalidAssignmentTarget => f.write_str("can't assign to this"),
+            ParseErrorKind::MisplacedReceiver => {
+                f.write_str("'self' can only be the first parameter")
+            }
+            ParseErrorKind::DanglingAttribute => f.write_str("attribute without an item"),
+            ParseErrorKind::ExpectedItem => f.write_str("expected item"),
+            ParseErrorKind::ExpectedPattern => f.write_str("expected pattern"),
+            ParseErrorKind::DuplicateVisibility => f.write_str("more than one visibility"),
+            ParseErrorKind::VisibilityNotAllowed(item) => {
+                write!(f, "visibility not allowed on {item}")
+            }
+            ParseErrorKind::RequiredAfterDefault => {
+                f.write_str("entry without a default after one with a default")
+            }
+            ParseErrorKind::LiteralOutOfRange { literal, target } => {
+                write!(f, "`{literal}` is out of range for {target}")
+            }
+        }
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic {
+            message: error.to_string(),
+            span: error.span,
+        }
+    }
+}
+
+// A token as an error message names it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenDescription {
+    // A keyword or punctuator, shown as it's spelled.
+    Token(TokenType<'static>),
+    // A token with text of its own, like identifier `foo`.
+    Named(&'static str, String),
+    EndOfFile,
+}
+
+impl TokenDescription {
+    pub fn of(token: &Token) -> Self {
+        let named = |kind| TokenDescription::Named(kind, token.lexeme().to_string());
+        match &token.r#type {
+            TokenType::EndOfFile => TokenDescription::EndOfFile,
+            TokenType::Identifier(_) => named("identifier"),
+            TokenType::MacroIdentifier(_) => named("macro"),
+            TokenType::Bits { .. } | TokenType::Integer { .. } | TokenType::Decimal { .. } => {
+                named("number")
+            }
+            TokenType::String(_) | TokenType::InterpolatedString(_) => named("string"),
+            TokenType::Character { .. } => named("character"),
+            r#type if r#type.keyword_str().is_some() || r#type.punctuator_str().is_some() => {
+                TokenDescription::Token(r#type.clone().into_owned())
+            }
+            _ => named("token"),
+        }
+    }
+}
+
+impl fmt::Display for TokenDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenDescription::Token(r#type) => {
+                let spelling = r#type.keyword_str().or_else(|| r#type.punctuator_str());
+                write!(f, "'{}'", spelling.unwrap_or_default())
+            }
+            TokenDescription::Named(kind, text) => write!(f, "{kind} `{text}`"),
+            TokenDescription::EndOfFile => f.write_str("end of file"),
+        }
+    }
+}
+
+// The tokens of a `Lexer` as the parser reads them, with as much lookahead as
+// it asks for. Doc comments are left out, but kept aside as trivia for
+// something like a formatter if it asks for them.
+pub struct TokenStream<'a> {
+    lexer: Lexer<'a>,
+    // The tokens read ahead, starting with the next one, which is always
+    // there.
+    buffer: VecDeque<Token<'a>>,
+    // Where the last consumed token ends.
+    previous_end: Position,
+    // How many `{` have been consumed but not closed.
+    brace_depth: usize,
+    errors: Vec<LexingError>,
+    trivia: Option<Vec<Token<'a>>>,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        Self::with(lexer, None)
+    }
+
+    // A stream that keeps the doc comments it skips, for `trivia`.
+    pub fn with_trivia(lexer: Lexer<'a>) -> Self {
+        Self::with(lexer, Some(Vec::new()))
+    }
+
+    fn with(lexer: Lexer<'a>, trivia: Option<Vec<Token<'a>>>) -> Self {
+        let previous_end = lexer.position().clone();
+        let mut stream = Self {
+            lexer,
+            buffer: VecDeque::new(),
+            previous_end,
+            brace_depth: 0,
+            errors: Vec::new(),
+            trivia,
+        };
+        stream.fill(0);
+        stream
+    }
+
+    // A stream over tokens that were already lexed, like those of an
+    // interpolation, with its `EndOfFile` at `end`.
+    fn from_tokens(tokens: Vec<Token<'a>>, end: Position) -> Self {
+        let mut stream = Self::new(Lexer::resume("", end));
+        if let Some(first) = tokens.first() {
+            stream.previous_end = first.span.start.clone();
+        }
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| !matches!(token.r#type, TokenType::DocComment(_)));
+        for token in tokens.rev() {
+            stream.buffer.push_front(token);
+        }
+        stream
+    }
+
+    // Reads tokens until there are `n + 1` ahead, setting aside any errors on
+    // the way.
+    fn fill(&mut self, n: usize) {
+        while self.buffer.len() <= n {
+            let token = match self.lexer.next() {
+                Some(Ok(token)) if matches!(token.r#type, TokenType::DocComment(_)) => {
+                    if let Some(trivia) = &mut self.trivia {
+                        trivia.push(token);
+                    }
+                    continue;
+                }
+                Some(Ok(token)) => token,
+                Some(Err(error)) => {
+                    self.errors.push(error);
+                    continue;
+                }
+                // Past the end, there's always another `EndOfFile`.
+                None => match self.buffer.back() {
+                    Some(last) => last.clone(),
+                    None => {
+                        let mut end = Token::new(self.lexer.position().clone());
+                        end.r#type = TokenType::EndOfFile;
+                        end
+                    }
+                },
+            };
+            self.buffer.push_back(token);
+        }
+    }
+
+    // The next token.
+    pub fn peek(&self) -> &Token<'a> {
+        &self.buffer[0]
+    }
+
+    // The token `n` places after the next one, so `peek_nth(0)` is `peek()`.
+    pub fn peek_nth(&mut self, n: usize) -> &Token<'a> {
+        self.fill(n);
+        &self.buffer[n]
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Token<'a> {
+        let token = self.buffer.pop_front().unwrap();
+        self.fill(0);
+        self.previous_end = token.span.end.clone();
+        match token.r#type {
+            TokenType::LeftCurlyBracket => self.brace_depth += 1,
+            TokenType::RightCurlyBracket => self.brace_depth = self.brace_depth.saturating_sub(1),
+            _ => {}
+        }
+        token
+    }
+
+    pub fn at(&self, r#type: &TokenType) -> bool {
+        self.peek().r#type == *r#type
+    }
+
+    pub fn eat(&mut self, r#type: &TokenType) -> Option<Token<'a>> {
+        self.at(r#type).then(|| self.next())
+    }
+
+    // Consumes the next token if it's one of `types`, with `context` saying
+    // where the parser is for the error if it isn't.
+    pub fn expect(
+        &mut self,
+        types: &[TokenType<'static>],
+        context: &'static str,
+    ) -> Result<Token<'a>, ParseError> {
+        if types.iter().any(|r#type| self.at(r#type)) {
+            return Ok(self.next());
         }
+        Err(ParseError {
+            kind: ParseErrorKind::Expected {
+                expected: types.iter().cloned().map(TokenDescription::Token).collect(),
+                found: TokenDescription::of(self.peek()),
+                context,
+            },
+            span: self.peek().span.clone(),
+        })
     }
+
+    // Consumes the first `>` of a next `>>`, such as where it closes nested
+    // generics like `List<List<int>>`, leaving the second.
+    pub fn split_double_right_angle(&mut self) -> Option<Token<'a>> {
+        let (first, second) = self.peek().split_double_right_angle()?;
+        self.buffer[0] = second;
+        self.previous_end = first.span.end.clone();
+        Some(first)
+    }
+
+    pub fn previous_end(&self) -> &Position {
+        &self.previous_end
+    }
+
+    pub fn brace_depth(&self) -> usize {
+        self.brace_depth
+    }
+
+    pub fn errors(&self) -> &[LexingError] {
+        &self.errors
+    }
+
+    // The doc comments skipped so far, if the stream was made to keep them.
+    pub fn trivia(&self) -> &[Token<'a>] {
+        self.trivia.as_deref().unwrap_or_default()
+    }
+}
+
+// Parses the tokens from a `Lexer` into a syntax tree, collecting errors
+// rather than stopping at the first one.
+pub struct Parser<'a> {
+    tokens: TokenStream<'a>,
+    // Where the parser is, for the messages of errors, like "in use
+    // declaration".
+    context: &'static str,
+    // Whether a path followed by `{` starts a struct literal, which it
+    // doesn't in the condition of an `if`, where the `{` starts its block.
+    struct_literals: bool,
+    // Whether a bracket left open at the end of the file has been reported,
+    // so the brackets around it, which hit the end too, don't report again.
+    unclosed_at_end: bool,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        Self {
+            tokens: TokenStream::new(lexer),
+            context: "at the start of the file",
+            struct_literals: true,
+            unclosed_at_end: false,
+            errors: Vec::new(),
+        }
+    }
+
+    // Parses a whole source file, giving its module, if the header could be
+    // parsed, along with every lexing and parsing error in source order.
+    pub fn parse(source: &str) -> (Option<ast::Module>, Vec<Diagnostic>) {
+        let mut parser = Parser::new(Lexer::from_str(source));
+        let module = parser.parse_module();
+        let mut diagnostics: Vec<Diagnostic> = parser
+            .tokens
+            .errors
+            .into_iter()
+            .map(Diagnostic::from)
+            .chain(parser.errors.into_iter().map(Diagnostic::from))
+            .collect();
+        diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.span.start.row, diagnostic.span.start.column));
+        (module, diagnostics)
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    pub fn lexing_errors(&self) -> &[LexingError] {
+        self.tokens.errors()
+    }
+
+    // module-declaration ::= 'module' path ';'
+    pub fn parse_module(&mut self) -> Option<ast::Module> {
+        let start = self.tokens.peek().span.start.clone();
+        if let Err(error) = self.expect(TokenType::Module) {
+            self.errors.push(error);
+            return None;
+        }
+
+        self.context = "in module declaration";
+        let name = match self.parse_path() {
+            Ok(name) => name,
+            Err(error) => {
+                self.errors.push(error);
+                return None;
+            }
+        };
+
+        self.expect_semicolon();
+        self.context = "in module";
+
+        let mut items = Vec::new();
+        while !self.at(&TokenType::EndOfFile) {
+            let item = self
+                .parse_item()
+                .unwrap_or_else(|| self.skip_stray_tokens());
+            items.push(item);
+        }
+
+        Some(ast::Module {
+            name,
+            items,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses the item starting at the current token, giving `None` if no item
+    // starts there, or an error item if one does but has errors.
+    fn parse_item(&mut self) -> Option<ast::Item> {
+        let start = self.tokens.peek().span.start.clone();
+        let depth = self.tokens.brace_depth();
+        let (attributes, visibility) = match self
+            .parse_attributes()
+            .and_then(|attributes| Ok((attributes, self.parse_visibility()?)))
+        {
+            Ok(prefix) => prefix,
+            Err(error) => return Some(self.recover_item(error, start, depth)),
+        };
+        let result = match self.tokens.peek().r#type {
+            TokenType::Use => self.in_context("in use declaration", Self::parse_use),
+            TokenType::Extend => self.in_context("in extend block", Self::parse_extend),
+            TokenType::Function => self.in_context("in function declaration", Self::parse_function),
+            TokenType::Value => self.in_context("in value declaration", Self::parse_value),
+            TokenType::Trait => self.in_context("in trait declaration", Self::parse_trait),
+            TokenType::Type => self.in_context("in type declaration", Self::parse_type_decl),
+            TokenType::Alias => {
+                // Aliases belong to traits and extend blocks, but the rest of
+                // one is skipped over all the same.
+                let keyword = self.tokens.peek().span.clone();
+                self.in_context("in alias declaration", |parser| parser.parse_alias(true))
+                    .and(Err(ParseError {
+                        kind: ParseErrorKind::ItemNotAllowed("module"),
+                        span: keyword,
+                    }))
+            }
+            _ if visibility.is_some() => Err(self.expected(&[
+                TokenType::Type,
+                TokenType::Trait,
+                TokenType::Function,
+                TokenType::Value,
+            ])),
+            _ if attributes.is_empty() => return None,
+            _ => Err(dangling_attribute(&attributes)),
+        };
+        Some(match result {
+            Ok(item) => with_attributes(with_visibility(item, visibility), attributes),
+            Err(error) => self.recover_item(error, start, depth),
+        })
+    }
+
+    // Skips tokens up to where an item could start, leaving an error item in
+    // their place, so a run of them like `; ;` is only reported once.
+    fn skip_stray_tokens(&mut self) -> ast::Item {
+        let start = self.tokens.peek().span.start.clone();
+        self.report(self.error(ParseErrorKind::ExpectedItem));
+        loop {
+            self.bump();
+            let r#type = &self.tokens.peek().r#type;
+            if *r#type == TokenType::EndOfFile
+                || starts_item(r#type)
+                || r#type.is_item_modifier()
+                || matches!(r#type, TokenType::MacroIdentifier(_))
+            {
+                break;
+            }
+        }
+        ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Error,
+            span: self.span_from(start),
+        }
+    }
+
+    // Reports `error` and skips the rest of the item it's in, leaving an error
+    // item in its place.
+    fn recover_item(&mut self, error: ParseError, start: Position, depth: usize) -> ast::Item {
+        self.errors.push(error);
+        ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Error,
+            span: self.synchronize(start, depth),
+        }
+    }
+
+    // Skips tokens up to somewhere parsing can pick up again: past a `;`, or
+    // before the keyword of an item or the `}` closing the block that was
+    // `depth` deep at `start`, giving the span of what was skipped. At least
+    // one token is skipped if nothing has been since `start`, so the same
+    // error can't be hit again.
+    fn synchronize(&mut self, start: Position, depth: usize) -> Span {
+        loop {
+            let progressed = self.tokens.peek().span.start != start;
+            match self.tokens.peek().r#type {
+                TokenType::EndOfFile => break,
+                TokenType::Semicolon => {
+                    self.bump();
+                    break;
+                }
+                TokenType::RightCurlyBracket
+                    if progressed && self.tokens.brace_depth() <= depth =>
+                {
+                    break
+                }
+                ref r#type if progressed && (starts_item(r#type) || r#type.is_item_modifier()) => {
+                    break
+                }
+                _ => {
+                    self.bump();
+                }
+            }
+        }
+        self.span_from(start)
+    }
+
+    // attribute ::= macro-call
+    fn parse_attributes(&mut self) -> Result<Vec<ast::MacroCall>, ParseError> {
+        let mut attributes = Vec::new();
+        while let TokenType::MacroIdentifier(_) = self.tokens.peek().r#type {
+            attributes.push(self.in_context("in attribute", Self::parse_macro_call)?);
+        }
+        Ok(attributes)
+    }
+
+    // visibility ::= 'public' | 'private'
+    //
+    // Gives the keyword, if there is one, which can't come before what
+    // doesn't have a visibility of its own, like a use declaration.
+    fn parse_visibility(&mut self) -> Result<Option<Token<'a>>, ParseError> {
+        if !self.tokens.peek().r#type.is_item_modifier() {
+            return Ok(None);
+        }
+        let keyword = self.bump();
+        if self.tokens.peek().r#type.is_item_modifier() {
+            return Err(self.error(ParseErrorKind::DuplicateVisibility));
+        }
+        let item = match self.tokens.peek().r#type {
+            TokenType::Module => "module declaration",
+            TokenType::Use => "use declaration",
+            TokenType::Extend => "extend block",
+            _ => return Ok(Some(keyword)),
+        };
+        Err(ParseError {
+            kind: ParseErrorKind::VisibilityNotAllowed(item),
+            span: keyword.span,
+        })
+    }
+
+    // macro-call ::= macro-identifier arguments
+    fn parse_macro_call(&mut self) -> Result<ast::MacroCall, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let TokenType::MacroIdentifier(name) = &self.tokens.peek().r#type else {
+            return Err(self.error(ParseErrorKind::ExpectedIdentifier));
+        };
+        let name = name.to_string();
+        let token = self.bump();
+        let args = self.parse_arguments()?;
+        Ok(ast::MacroCall {
+            name: ast::Ident {
+                name,
+                span: token.span,
+            },
+            args,
+            span: self.span_from(start),
+        })
+    }
+
+    // use-declaration ::= 'use' use-tree ';'
+    fn parse_use(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Use)?.span.start;
+        let decl = self.parse_use_tree()?;
+        self.expect_semicolon();
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Use(decl),
+            span: self.span_from(start),
+        })
+    }
+
+    // extend-block ::= 'extend' type [':' path {',' path}] '{' {extend-item} '}'
+    fn parse_extend(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Extend)?.span.start;
+        let target = self.parse_type()?;
+
+        let mut traits = Vec::new();
+        if self.eat(&TokenType::Colon).is_some() {
+            traits.push(self.parse_path()?);
+            while self.eat(&TokenType::Comma).is_some() {
+                traits.push(self.parse_path()?);
+            }
+        }
+
+        let items = self.parse_item_block(Self::parse_extend_item)?;
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Extend(ast::ExtendBlock {
+                target,
+                traits,
+                items,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // extend-item ::= function-declaration | value-declaration | alias-declaration
+    fn parse_extend_item(&mut self) -> Result<ast::Item, ParseError> {
+        match self.tokens.peek().r#type {
+            TokenType::Function => self.in_context("in function declaration", Self::parse_function),
+            TokenType::Value => self.in_context("in value declaration", Self::parse_value),
+            TokenType::Alias => {
+                self.in_context("in alias declaration", |parser| parser.parse_alias(true))
+            }
+            TokenType::Module
+            | TokenType::Trait
+            | TokenType::Type
+            | TokenType::Extend
+            | TokenType::Use => Err(self.error(ParseErrorKind::ItemNotAllowed("extend block"))),
+            _ => Err(self.expected(&[
+                TokenType::Function,
+                TokenType::Value,
+                TokenType::Alias,
+                TokenType::RightCurlyBracket,
+            ])),
+        }
+    }
+
+    // trait-declaration ::= 'trait' identifier [generic-parameters] '{' {trait-item} '}'
+    fn parse_trait(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Trait)?.span.start;
+        let name = self.expect_identifier()?;
+        let generics = self.in_context("in generic parameters", Self::parse_generic_parameters)?;
+        let items = self.parse_item_block(Self::parse_trait_item)?;
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Trait(ast::TraitDecl {
+                name,
+                generics,
+                items,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // type-declaration ::= 'type' identifier [generic-parameters] [':' type]
+    //                      '=' field [default] {',' field [default]} ';'
+    fn parse_type_decl(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Type)?.span.start;
+        let name = self.expect_identifier()?;
+        let generics = self.in_context("in generic parameters", Self::parse_generic_parameters)?;
+        let repr = match self.eat(&TokenType::Colon) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+        self.expect(TokenType::EqualsSign)?;
+
+        let mut fields = Vec::new();
+        loop {
+            let name = self.expect_field_name()?;
+            let mut field = self.parse_field_type(name)?;
+            field.default = self.parse_default()?;
+            field.span = self.span_from(field.span.start.clone());
+            let follows_default = fields
+                .last()
+                .is_some_and(|field: &ast::Field| field.default.is_some());
+            self.check_default_order(follows_default, &field.default, &field.span);
+            fields.push(field);
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect_semicolon();
+
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Type(ast::TypeDecl {
+                name,
+                generics,
+                repr,
+                fields,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // trait-item ::= function-declaration | alias-declaration
+    fn parse_trait_item(&mut self) -> Result<ast::Item, ParseError> {
+        match self.tokens.peek().r#type {
+            TokenType::Function => self.in_context("in function declaration", Self::parse_function),
+            // Leaving out the type requires whatever extends the trait to
+            // give one.
+            TokenType::Alias => {
+                self.in_context("in alias declaration", |parser| parser.parse_alias(false))
+            }
+            TokenType::Module
+            | TokenType::Trait
+            | TokenType::Type
+            | TokenType::Extend
+            | TokenType::Use
+            | TokenType::Value => Err(self.error(ParseErrorKind::ItemNotAllowed("trait"))),
+            _ => Err(self.expected(&[
+                TokenType::Function,
+                TokenType::Alias,
+                TokenType::RightCurlyBracket,
+            ])),
+        }
+    }
+
+    // Parses items with `item` between curly brackets.
+    fn parse_item_block(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<ast::Item, ParseError>,
+    ) -> Result<Vec<ast::Item>, ParseError> {
+        self.expect(TokenType::LeftCurlyBracket)?;
+        let mut items = Vec::new();
+        while !self.at(&TokenType::RightCurlyBracket) && !self.at(&TokenType::EndOfFile) {
+            let start = self.tokens.peek().span.start.clone();
+            let depth = self.tokens.brace_depth();
+            let result = self.parse_attributes().and_then(|attributes| {
+                let visibility = self.parse_visibility()?;
+                if !attributes.is_empty()
+                    && (self.at(&TokenType::RightCurlyBracket) || self.at(&TokenType::EndOfFile))
+                {
+                    return Err(dangling_attribute(&attributes));
+                }
+                let item = with_visibility(item(self)?, visibility);
+                Ok(with_attributes(item, attributes))
+            });
+            items.push(result.unwrap_or_else(|error| self.recover_item(error, start, depth)));
+        }
+        if !(self.unclosed_at_end && self.at(&TokenType::EndOfFile)) {
+            self.expect(TokenType::RightCurlyBracket)?;
+        }
+        Ok(items)
+    }
+
+    // alias-declaration ::= 'alias' identifier [generic-parameters] ['=' type] ';'
+    fn parse_alias(&mut self, target_required: bool) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Alias)?.span.start;
+        let name = self.expect_identifier()?;
+        let generics = self.in_context("in generic parameters", Self::parse_generic_parameters)?;
+        let target = if self.eat(&TokenType::EqualsSign).is_some() {
+            Some(self.parse_type()?)
+        } else if target_required {
+            return Err(self.expected(&[TokenType::EqualsSign]));
+        } else {
+            None
+        };
+        self.expect_semicolon();
+
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Alias(ast::AliasDecl {
+                name,
+                generics,
+                target,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    // function-declaration ::= 'function' identifier [generic-parameters]
+    //                          '(' [parameter {',' parameter} [',']] ')' ['->' type]
+    //                          (block | '=' expression ';' | ';')
+    fn parse_function(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.expect(TokenType::Function)?.span.start;
+        let name = self.expect_identifier()?;
+        let generics = self.in_context("in generic parameters", Self::parse_generic_parameters)?;
+
+        self.expect(TokenType::LeftParenthesis)?;
+        let mut receiver = None;
+        let mut params = Vec::new();
+        while !self.at(&TokenType::RightParenthesis) {
+            if self.at_receiver() {
+                let parsed = self.parse_receiver();
+                if receiver.is_some() || !params.is_empty() {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::MisplacedReceiver,
+                        span: parsed.span,
+                    });
+                }
+                receiver = Some(parsed);
+            } else {
+                let param = self.parse_parameter()?;
+                let follows_default = params
+                    .last()
+                    .is_some_and(|param: &ast::Param| param.default.is_some());
+                self.check_default_order(follows_default, &param.default, &param.span);
+                params.push(param);
+            }
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenType::RightParenthesis)?;
+
+        let return_type = if self.eat(&TokenType::RightwardsArrow).is_some() {
+            Some(self.parse_type()?)
+        } else if starts_type(&self.tokens.peek().r#type) {
+            return Err(self.expected(&[TokenType::RightwardsArrow]));
+        } else {
+            None
+        };
+
+        let body = match self.tokens.peek().r#type {
+            TokenType::LeftCurlyBracket => Some(ast::FunctionBody::Block(self.parse_block()?)),
+            TokenType::EqualsSign => {
+                self.bump();
+                let body = self.parse_expr()?;
+                self.expect_semicolon();
+                Some(ast::FunctionBody::Expr(body))
+            }
+            _ => {
+                self.expect_one_of(&[
+                    TokenType::LeftCurlyBracket,
+                    TokenType::EqualsSign,
+                    TokenType::Semicolon,
+                ])?;
+                None
+            }
+        };
+
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Function(ast::FunctionDecl {
+                name,
+                generics,
+                receiver,
+                params,
+                return_type,
+                body,
+            }),
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_value(&mut self) -> Result<ast::Item, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let decl = self.parse_value_decl()?;
+        Ok(ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Value(decl),
+            span: self.span_from(start),
+        })
+    }
+
+    // value-declaration ::= 'value' [mutability] identifier [':' type] ['=' expression] ';'
+    fn parse_value_decl(&mut self) -> Result<ast::ValueDecl, ParseError> {
+        self.expect(TokenType::Value)?;
+        let mutability = match self.tokens.peek().r#type {
+            TokenType::Mutable => ast::Mutability::Mutable,
+            TokenType::Constant => ast::Mutability::Constant,
+            _ => ast::Mutability::Default,
+        };
+        if mutability != ast::Mutability::Default {
+            self.bump();
+        }
+
+        let name = self.expect_identifier()?;
+        let ty = match self.eat(&TokenType::Colon) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+        let init = match self.eat(&TokenType::EqualsSign) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+        // Without either, there'd be no way to tell the value's type.
+        if ty.is_none() && init.is_none() {
+            return Err(ParseError {
+                kind: ParseErrorKind::MissingTypeAndInitializer,
+                span: name.span,
+            });
+        }
+        self.expect_semicolon();
+
+        Ok(ast::ValueDecl {
+            mutability,
+            name,
+            ty,
+            init,
+        })
+    }
+
+    // parameter ::= type | identifier ':' type
+    fn parse_parameter(&mut self) -> Result<ast::Param, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let ty = self.parse_type()?;
+        let (name, ty) = if self.at(&TokenType::Colon) {
+            let field = self.parse_field_type(single_name(ty)?)?;
+            (Some(field.name), field.ty)
+        } else {
+            (None, ty)
+        };
+        let default = self.parse_default()?;
+        Ok(ast::Param {
+            name,
+            ty,
+            default,
+            span: self.span_from(start),
+        })
+    }
+
+    // default ::= '=' expression
+    fn parse_default(&mut self) -> Result<Option<ast::Expr>, ParseError> {
+        match self.eat(&TokenType::EqualsSign) {
+            Some(_) => Ok(Some(self.parse_expr()?)),
+            None => Ok(None),
+        }
+    }
+
+    // Reports an entry spanning `span` without a default that follows one
+    // with a default, as there'd be no way to give it a value while leaving
+    // out the one before it.
+    fn check_default_order(
+        &mut self,
+        follows_default: bool,
+        default: &Option<ast::Expr>,
+        span: &Span,
+    ) {
+        if follows_default && default.is_none() {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::RequiredAfterDefault,
+                span: span.clone(),
+            });
+        }
+    }
+
+    // Whether a receiver starts here, which for `@self` and `@?self` takes
+    // looking past the `@`.
+    fn at_receiver(&mut self) -> bool {
+        match self.tokens.peek().r#type {
+            TokenType::LowerSelf => true,
+            TokenType::ComercialAt => match self.tokens.peek_nth(1).r#type {
+                TokenType::LowerSelf => true,
+                TokenType::QuestionMark => self.tokens.peek_nth(2).r#type == TokenType::LowerSelf,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // receiver ::= ['@' ['?']] 'self'
+    fn parse_receiver(&mut self) -> ast::Receiver {
+        let start = self.tokens.peek().span.start.clone();
+        let pointer = self.eat(&TokenType::ComercialAt).is_some();
+        let nullable = self.eat(&TokenType::QuestionMark).is_some();
+        self.bump();
+        ast::Receiver {
+            pointer,
+            nullable,
+            span: self.span_from(start),
+        }
+    }
+
+    // generic-parameters ::= '<' identifier {',' identifier} '>'
+    fn parse_generic_parameters(&mut self) -> Result<Vec<ast::Ident>, ParseError> {
+        let mut generics = Vec::new();
+        if self.eat(&TokenType::LeftAngleBracket).is_none() {
+            return Ok(generics);
+        }
+        generics.push(self.expect_identifier()?);
+        while self.eat(&TokenType::Comma).is_some() {
+            generics.push(self.expect_identifier()?);
+        }
+        self.expect(TokenType::RightAngleBracket)?;
+        Ok(generics)
+    }
+
+    // generic-arguments ::= '<' generic-argument {',' generic-argument} '>'
+    // generic-argument ::= type | unary-expression
+    //
+    // A constant argument is cut short at the unary level so its `>` isn't
+    // read as greater-than, and anything in parentheses is taken as a type.
+    fn parse_generic_arguments(&mut self) -> Result<Vec<ast::GenericArg>, ParseError> {
+        let mut args = Vec::new();
+        let Some(open) = self.eat(&TokenType::LeftAngleBracket) else {
+            return Ok(args);
+        };
+        loop {
+            args.push(if starts_type(&self.tokens.peek().r#type) {
+                ast::GenericArg::Type(self.parse_type()?)
+            } else {
+                ast::GenericArg::Const(self.parse_unary()?)
+            });
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect_right_angle(open)?;
+        Ok(args)
+    }
+
+    // block ::= '{' {statement} '}'
+    fn parse_block(&mut self) -> Result<ast::Block, ParseError> {
+        let open = self.expect(TokenType::LeftCurlyBracket)?;
+        let start = open.span.start.clone();
+        let mut stmts = Vec::new();
+        // An item keyword other than `value` can't start a statement, so it's
+        // taken to mean the `}` was forgotten.
+        while !self.at(&TokenType::RightCurlyBracket)
+            && !self.at(&TokenType::EndOfFile)
+            && (self.at(&TokenType::Value) || !starts_item(&self.tokens.peek().r#type))
+        {
+            let start = self.tokens.peek().span.start.clone();
+            let depth = self.tokens.brace_depth();
+            let stmt = self.parse_stmt().unwrap_or_else(|error| {
+                self.report(error);
+                ast::Stmt {
+                    kind: ast::StmtKind::Error,
+                    span: self.synchronize(start, depth),
+                }
+            });
+            stmts.push(stmt);
+        }
+        if !(self.unclosed_at_end && self.at(&TokenType::EndOfFile)) {
+            self.expect_closing(TokenType::RightCurlyBracket, open)?;
+        }
+
+        // An expression statement with nothing after it, not even a `;`,
+        // gives the block its value.
+        let tail = match stmts.pop() {
+            Some(ast::Stmt {
+                kind: ast::StmtKind::Expr(expr),
+                span,
+            }) if span == expr.span => Some(Box::new(expr)),
+            last => {
+                stmts.extend(last);
+                None
+            }
+        };
+        Ok(ast::Block {
+            stmts,
+            tail,
+            span: self.span_from(start),
+        })
+    }
+
+    // statement ::= value-declaration | return-statement | block | if-expression
+    //             | match-expression | while-loop | for-loop | 'break' ';' | 'continue' ';'
+    //             | expression [assignment-operator expression] ';'
+    //             | expression
+    // assignment-operator ::= '=' | '+=' | '-=' | '*=' | '/=' | '%=' | '^='
+    fn parse_stmt(&mut self) -> Result<ast::Stmt, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        if self.at(&TokenType::Value) {
+            let decl = self.in_context("in value declaration", Self::parse_value_decl)?;
+            return Ok(ast::Stmt {
+                kind: ast::StmtKind::Local(decl),
+                span: self.span_from(start),
+            });
+        }
+        if self.eat(&TokenType::Return).is_some() {
+            return self.in_context("in return statement", |parser| parser.parse_return(start));
+        }
+        if self.tokens.peek().r#type.is_item_modifier()
+            && self.tokens.peek_nth(1).r#type == TokenType::Value
+        {
+            return Err(self.error(ParseErrorKind::VisibilityNotAllowed("local value")));
+        }
+        let kind = match self.tokens.peek().r#type {
+            TokenType::While => Some(self.parse_while()?),
+            TokenType::For => Some(self.in_context("in for loop", Self::parse_for)?),
+            TokenType::Break | TokenType::Continue => {
+                let keyword = self.bump();
+                let context = match keyword.r#type {
+                    TokenType::Break => "after break",
+                    _ => "after continue",
+                };
+                self.in_context(context, Self::expect_semicolon);
+                Some(match keyword.r#type {
+                    TokenType::Break => ast::StmtKind::Break,
+                    _ => ast::StmtKind::Continue,
+                })
+            }
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            return Ok(ast::Stmt {
+                kind,
+                span: self.span_from(start),
+            });
+        }
+        // A block, `if` or `match` on its own needs no `;`, and ends the
+        // statement there even if something like an operator comes after it.
+        if self.at(&TokenType::LeftCurlyBracket)
+            || self.at(&TokenType::If)
+            || self.at(&TokenType::Match)
+        {
+            let expr = self.parse_primary()?;
+            return Ok(ast::Stmt {
+                kind: ast::StmtKind::Expr(expr),
+                span: self.span_from(start),
+            });
+        }
+
+        let expr = self.parse_expr()?;
+        let op = match self.tokens.peek().r#type {
+            TokenType::EqualsSign => None,
+            TokenType::PlusEquals => Some(ast::BinaryOp::Add),
+            TokenType::MinusEquals => Some(ast::BinaryOp::Subtract),
+            TokenType::AsteriskEquals => Some(ast::BinaryOp::Multiply),
+            TokenType::SolidusEquals => Some(ast::BinaryOp::Divide),
+            TokenType::PercentEquals => Some(ast::BinaryOp::Remainder),
+            TokenType::CircumflexEquals => Some(ast::BinaryOp::BitXor),
+            // The last expression in a block can leave out its `;` to be the
+            // block's value.
+            TokenType::RightCurlyBracket => {
+                return Ok(ast::Stmt {
+                    kind: ast::StmtKind::Expr(expr),
+                    span: self.span_from(start),
+                })
+            }
+            _ => {
+                self.in_context("after expression", |parser| {
+                    parser.expect(TokenType::Semicolon)
+                })?;
+                return Ok(ast::Stmt {
+                    kind: ast::StmtKind::Expr(expr),
+                    span: self.span_from(start),
+                });
+            }
+        };
+        if !is_place(&expr) {
+            return Err(ParseError {
+                kind: ParseErrorKind::InvalidAssignmentTarget,
+                span: expr.span,
+            });
+        }
+        self.bump();
+        let value = self.parse_expr()?;
+        self.in_context("after assignment", |parser| {
+            parser.expect(TokenType::Semicolon)
+        })?;
+        Ok(ast::Stmt {
+            kind: ast::StmtKind::Assign {
+                target: expr,
+                op,
+                value,
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    // while-loop ::= 'while' expression block
+    fn parse_while(&mut self) -> Result<ast::StmtKind, ParseError> {
+        self.expect(TokenType::While)?;
+        let condition = self.with_struct_literals(false, Self::parse_expr)?;
+        let body = self.parse_body("in while loop")?;
+        Ok(ast::StmtKind::While { condition, body })
+    }
+
+    // for-loop ::= 'for' (identifier ':' type | pattern) 'in' expression block
+    fn parse_for(&mut self) -> Result<ast::StmtKind, ParseError> {
+        self.expect(TokenType::For)?;
+        // A name with a `:` after it has its type given, which a pattern
+        // otherwise would take for an alternative of an algebraic type.
+        let (binding, ty) = if matches!(self.tokens.peek().r#type, TokenType::Identifier(_))
+            && self.tokens.peek_nth(1).r#type == TokenType::Colon
+        {
+            let name = self.expect_identifier()?;
+            self.bump();
+            let binding = ast::Pattern {
+                span: name.span.clone(),
+                kind: ast::PatternKind::Binding(name),
+            };
+            (binding, Some(self.parse_type()?))
+        } else {
+            (self.parse_pattern()?, None)
+        };
+        self.expect(TokenType::In)?;
+        let iterable = self.with_struct_literals(false, Self::parse_expr)?;
+        let body = self.parse_body("in for loop")?;
+        Ok(ast::StmtKind::For {
+            binding,
+            ty,
+            iterable,
+            body,
+        })
+    }
+
+    // return-statement ::= 'return' [expression] ';'
+    fn parse_return(&mut self, start: Position) -> Result<ast::Stmt, ParseError> {
+        let value = match self.tokens.peek().r#type {
+            TokenType::Semicolon | TokenType::RightCurlyBracket => None,
+            _ => Some(self.parse_expr()?),
+        };
+        // A missing `;` is reported but otherwise ignored, so the next
+        // statement still parses.
+        self.expect_semicolon();
+        Ok(ast::Stmt {
+            kind: ast::StmtKind::Return(value),
+            span: self.span_from(start),
+        })
+    }
+
+    // use-tree ::= identifier {'::' identifier} ['as' identifier | '::' use-group]
+    // use-group ::= '{' use-tree {',' use-tree} [','] '}'
+    fn parse_use_tree(&mut self) -> Result<ast::UseDecl, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let mut segments = vec![self.expect_identifier()?];
+        let mut group = None;
+        while self.eat(&TokenType::DoubleColon).is_some() {
+            if self.at(&TokenType::LeftCurlyBracket) {
+                group = Some(self.parse_use_group()?);
+                break;
+            }
+            segments.push(self.expect_identifier()?);
+        }
+
+        // The path ends before any group.
+        let end = segments.last().unwrap().span.end.clone();
+        let path = ast::Path {
+            segments,
+            span: Span { start, end },
+        };
+        let kind = match group {
+            Some(group) => ast::UseKind::Group(group),
+            None if self.eat(&TokenType::As).is_some() => ast::UseKind::Single {
+                alias: Some(self.expect_identifier()?),
+            },
+            None => ast::UseKind::Single { alias: None },
+        };
+        Ok(ast::UseDecl { path, kind })
+    }
+
+    fn parse_use_group(&mut self) -> Result<Vec<ast::UseDecl>, ParseError> {
+        let open = self.expect(TokenType::LeftCurlyBracket)?;
+        let mut group = Vec::new();
+        while !self.at(&TokenType::RightCurlyBracket) {
+            group.push(self.parse_use_tree()?);
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        if !self.at(&TokenType::RightCurlyBracket) {
+            return Err(self.expected(&[TokenType::Comma, TokenType::RightCurlyBracket]));
+        }
+        self.bump();
+
+        if group.is_empty() {
+            return Err(ParseError {
+                kind: ParseErrorKind::EmptyUseGroup,
+                span: self.span_from(open.span.start),
+            });
+        }
+        Ok(group)
+    }
+
+    // type ::= type-operand ['?'] | parameters '->' type
+    //
+    // The parameters of a function type are always in parentheses, which
+    // makes the arrow right-associative, as in `(int) -> (int) -> bool`. A
+    // `?` after one is part of its return type, so an optional function type
+    // needs its own parentheses, as in `((int) -> bool)?`.
+    pub fn parse_type(&mut self) -> Result<ast::TypeExpr, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let parenthesized = self.at(&TokenType::LeftParenthesis);
+        let operand = self.parse_type_operand()?;
+        if !parenthesized {
+            return Ok(self.parse_optional(start, operand));
+        }
+
+        if self.eat(&TokenType::RightwardsArrow).is_none() {
+            // A type straight after the parameters, as in `() int`, is most
+            // likely missing the arrow between them.
+            if starts_type(&self.tokens.peek().r#type) {
+                return Err(self.expected(&[TokenType::RightwardsArrow]));
+            }
+            return Ok(self.parse_optional(start, operand));
+        }
+
+        let ret = self.parse_type()?;
+        Ok(ast::TypeExpr {
+            kind: ast::TypeExprKind::Function {
+                params: parameters(operand),
+                ret: Box::new(ret),
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    // Makes `operand` optional if there's a `?` after it.
+    fn parse_optional(&mut self, start: Position, operand: ast::TypeExpr) -> ast::TypeExpr {
+        if self.eat(&TokenType::QuestionMark).is_none() {
+            return operand;
+        }
+        ast::TypeExpr {
+            kind: ast::TypeExprKind::Optional(Box::new(operand)),
+            span: self.span_from(start),
+        }
+    }
+
+    // type-operand ::= path | type-word | '@' ['?'] type | '(' type ')' | composite
+    fn parse_type_operand(&mut self) -> Result<ast::TypeExpr, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        if self.tokens.peek().r#type.is_type_word() {
+            let token = self.bump();
+            let path = ast::Path {
+                segments: vec![ast::Ident {
+                    name: token.lexeme().to_string(),
+                    span: token.span.clone(),
+                }],
+                span: token.span,
+            };
+            return Ok(ast::TypeExpr {
+                kind: ast::TypeExprKind::Named {
+                    path,
+                    args: Vec::new(),
+                },
+                span: self.span_from(start),
+            });
+        }
+
+        match self.tokens.peek().r#type {
+            TokenType::Identifier(_) => {
+                let path = self.parse_path()?;
+                let args =
+                    self.in_context("in generic arguments", Self::parse_generic_arguments)?;
+                Ok(ast::TypeExpr {
+                    kind: ast::TypeExprKind::Named { path, args },
+                    span: self.span_from(start),
+                })
+            }
+            TokenType::LeftParenthesis => {
+                self.in_context("in parenthesized type", Self::parse_parenthesized_type)
+            }
+            TokenType::ComercialAt => {
+                self.bump();
+                let nullable = self.eat(&TokenType::QuestionMark).is_some();
+                let pointee = self.parse_type()?;
+                Ok(ast::TypeExpr {
+                    kind: ast::TypeExprKind::Pointer {
+                        nullable,
+                        pointee: Box::new(pointee),
+                    },
+                    span: self.span_from(start),
+                })
+            }
+            _ => Err(self.error(ParseErrorKind::ExpectedType)),
+        }
+    }
+
+    // A type in parentheses, which is either just grouped or one of the
+    // composite types:
+    //
+    // tuple ::= '(' ')' | '(' type ',' [type {',' type} [',']] ')'
+    // algebraic ::= '(' field '|' field {'|' field} ')'
+    // tagged-tuple ::= '(' field {',' field} [','] ')'
+    // enumeration ::= '(' identifier '|' identifier {'|' identifier} ')'
+    // array ::= '(' type '*' expression ')'
+    // union ::= '(' type '+' type {'+' type} ')'
+    fn parse_parenthesized_type(&mut self) -> Result<ast::TypeExpr, ParseError> {
+        let start = self.expect(TokenType::LeftParenthesis)?.span.start;
+        if self.eat(&TokenType::RightParenthesis).is_some() {
+            return Ok(ast::TypeExpr {
+                kind: ast::TypeExprKind::Tuple(Vec::new()),
+                span: self.span_from(start),
+            });
+        }
+        let first = self.parse_type()?;
+
+        let kind = match self.tokens.peek().r#type {
+            TokenType::Colon => {
+                let field = self.parse_field_type(single_name(first)?)?;
+                if self.at(&TokenType::VerticalLine) {
+                    return self.parse_algebraic(start, field);
+                }
+
+                let mut fields = vec![field];
+                while self.eat(&TokenType::Comma).is_some() {
+                    if self.at(&TokenType::RightParenthesis) {
+                        break;
+                    }
+                    let name = self.expect_identifier()?;
+                    fields.push(self.parse_field_type(name)?);
+                }
+                ast::TypeExprKind::TaggedTuple(fields)
+            }
+            TokenType::Comma => {
+                let mut elements = vec![first];
+                while self.eat(&TokenType::Comma).is_some() {
+                    if self.at(&TokenType::RightParenthesis) {
+                        break;
+                    }
+                    elements.push(self.parse_type()?);
+                }
+                ast::TypeExprKind::Tuple(elements)
+            }
+            TokenType::VerticalLine => {
+                let mut alternatives = vec![single_name(first)?];
+                alternatives.extend(
+                    self.parse_separated(&TokenType::VerticalLine, Self::expect_identifier)?,
+                );
+                // A later alternative with a type makes this algebraic, which
+                // the first one then needs a type for too.
+                if self.at(&TokenType::Colon) {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::MissingPayload,
+                        span: alternatives.swap_remove(0).span,
+                    });
+                }
+                ast::TypeExprKind::Enumeration(alternatives)
+            }
+            TokenType::Asterisk => {
+                self.bump();
+                // Only the first `*` makes an array, so any after it multiply
+                // within the length, as in `(bit * 4 * 8)`.
+                ast::TypeExprKind::Array {
+                    element: Box::new(first),
+                    length: Box::new(self.parse_expr()?),
+                }
+            }
+            TokenType::PlusSign => {
+                let mut members = vec![first];
+                members.extend(self.parse_separated(&TokenType::PlusSign, Self::parse_type)?);
+                ast::TypeExprKind::Union(members)
+            }
+            _ => first.kind,
+        };
+
+        // Composite types don't mix without parentheses of their own, so
+        // `(A | B + C)` could be read more than one way.
+        if is_composite_separator(&self.tokens.peek().r#type) {
+            return Err(self.error(ParseErrorKind::AmbiguousComposite));
+        }
+        self.expect(TokenType::RightParenthesis)?;
+        Ok(ast::TypeExpr {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses the rest of an algebraic type after its first alternative, which
+    // started at `start`.
+    fn parse_algebraic(
+        &mut self,
+        start: Position,
+        first: ast::Field,
+    ) -> Result<ast::TypeExpr, ParseError> {
+        let mut variants = vec![(first.name, first.ty)];
+        let rest = self.parse_separated(&TokenType::VerticalLine, |parser| {
+            let name = parser.expect_identifier()?;
+            if !parser.at(&TokenType::Colon) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::MissingPayload,
+                    span: name.span,
+                });
+            }
+            let field = parser.parse_field_type(name)?;
+            Ok((field.name, field.ty))
+        })?;
+
+        for (name, ty) in rest {
+            if variants.iter().any(|(other, _)| other.name == name.name) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::DuplicateVariant(name.name),
+                    span: name.span,
+                });
+            }
+            variants.push((name, ty));
+        }
+
+        if is_composite_separator(&self.tokens.peek().r#type) {
+            return Err(self.error(ParseErrorKind::AmbiguousComposite));
+        }
+        self.expect(TokenType::RightParenthesis)?;
+        Ok(ast::TypeExpr {
+            kind: ast::TypeExprKind::Algebraic(variants),
+            span: self.span_from(start),
+        })
+    }
+
+    // field ::= identifier ':' type
+    //
+    // Parses the rest of a field whose name has already been parsed.
+    fn parse_field_type(&mut self, name: ast::Ident) -> Result<ast::Field, ParseError> {
+        let start = name.span.start.clone();
+        self.expect(TokenType::Colon)?;
+        let ty = self.parse_type()?;
+        Ok(ast::Field {
+            name,
+            ty,
+            default: None,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses an element after each `separator`, rejecting a separator with no
+    // element after it.
+    fn parse_separated<T>(
+        &mut self,
+        separator: &TokenType,
+        mut element: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut elements = Vec::new();
+        while let Some(token) = self.eat(separator) {
+            if self.at(&TokenType::RightParenthesis) {
+                return Err(ParseError {
+                    kind: ParseErrorKind::TrailingSeparator,
+                    span: token.span,
+                });
+            }
+            elements.push(element(self)?);
+        }
+        Ok(elements)
+    }
+
+    pub fn parse_expr(&mut self) -> Result<ast::Expr, ParseError> {
+        self.parse_binary(0)
+    }
+
+    // Parses operands joined by operators that bind at least as tightly as
+    // `min_precedence`, grouping operators of equal precedence to the left.
+    // Comparisons don't group at all, so `a < b < c` is an error rather than
+    // comparing a `bool` with `c`, and neither do ranges.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<ast::Expr, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let mut left = self.parse_unary()?;
+        while let Some((op, precedence)) = binary_operator(&self.tokens.peek().r#type) {
+            if precedence < min_precedence {
+                break;
+            }
+            self.bump();
+            let right = self.parse_binary(precedence + 1)?;
+            let chained = binary_operator(&self.tokens.peek().r#type)
+                .is_some_and(|(_, next)| next == precedence);
+            if chained && precedence == COMPARISON {
+                return Err(self.error(ParseErrorKind::ChainedComparison));
+            }
+            if chained && precedence == RANGE {
+                return Err(self.error(ParseErrorKind::ChainedRange));
+            }
+            left = ast::Expr {
+                kind: ast::ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                span: self.span_from(start.clone()),
+            };
+        }
+        Ok(left)
+    }
+
+    // unary ::= ('-' | '!' | '@' | '*') unary | postfix
+    fn parse_unary(&mut self) -> Result<ast::Expr, ParseError> {
+        // A sign before a number is part of the literal.
+        if self.at_signed_number() {
+            return self.parse_postfix();
+        }
+        let op = match self.tokens.peek().r#type {
+            TokenType::MinuxSign => ast::UnaryOp::Negate,
+            TokenType::ExclamationMark => ast::UnaryOp::Not,
+            TokenType::ComercialAt => ast::UnaryOp::AddressOf,
+            TokenType::Asterisk => ast::UnaryOp::Dereference,
+            _ => return self.parse_postfix(),
+        };
+
+        let start = self.bump().span.start;
+        let operand = self.parse_unary()?;
+        Ok(ast::Expr {
+            kind: ast::ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span: self.span_from(start),
+        })
+    }
+
+    // postfix ::= primary {arguments | '.' (identifier | tuple-index)}
+    fn parse_postfix(&mut self) -> Result<ast::Expr, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let mut expr = self.parse_primary()?;
+        loop {
+            let generic_call = self.at(&TokenType::LeftAngleBracket)
+                && matches!(expr.kind, ast::ExprKind::Path(_))
+                && self.at_generic_call();
+            let kind = match self.tokens.peek().r#type {
+                TokenType::LeftParenthesis => ast::ExprKind::Call {
+                    callee: Box::new(expr),
+                    generics: Vec::new(),
+                    args: self.in_context("in argument list", Self::parse_arguments)?,
+                },
+                TokenType::LeftAngleBracket if generic_call => {
+                    let generics =
+                        self.in_context("in generic arguments", Self::parse_generic_arguments)?;
+                    ast::ExprKind::Call {
+                        callee: Box::new(expr),
+                        generics,
+                        args: self.in_context("in argument list", Self::parse_arguments)?,
+                    }
+                }
+                TokenType::FullStop => {
+                    self.bump();
+                    match self.tokens.peek().r#type {
+                        TokenType::Bits { .. }
+                        | TokenType::Integer { .. }
+                        | TokenType::Decimal { .. } => {
+                            expr = self.parse_tuple_indices(expr, &start)?;
+                            continue;
+                        }
+                        _ => ast::ExprKind::Field {
+                            base: Box::new(expr),
+                            name: self.expect_identifier()?,
+                        },
+                    }
+                }
+                _ => return Ok(expr),
+            };
+            expr = ast::Expr {
+                kind,
+                span: self.span_from(start.clone()),
+            };
+        }
+    }
+
+    // Whether the `<` after a path opens generic arguments for a call rather
+    // than being a comparison. It does if the tokens up to its matching `>`
+    // could all be part of types or constants and a `(` comes straight after,
+    // so `f<int>(x)` is a call, and so is `a < b > (c)`, while `a < b > c` and
+    // `a < b` are comparisons. Only so many tokens are looked at, past which
+    // it's taken to be a comparison.
+    fn at_generic_call(&mut self) -> bool {
+        const LIMIT: usize = 32;
+        let mut depth = 0usize;
+        for n in 0..LIMIT {
+            match self.tokens.peek_nth(n).r#type {
+                TokenType::LeftAngleBracket => depth += 1,
+                TokenType::RightAngleBracket => depth -= 1,
+                TokenType::DoubleRightAngle if depth >= 2 => depth -= 2,
+                TokenType::Identifier(_)
+                | TokenType::Bits { .. }
+                | TokenType::Integer { .. }
+                | TokenType::Decimal { .. }
+                | TokenType::Comma
+                | TokenType::Colon
+                | TokenType::DoubleColon
+                | TokenType::LeftParenthesis
+                | TokenType::RightParenthesis
+                | TokenType::ComercialAt
+                | TokenType::QuestionMark
+                | TokenType::Asterisk
+                | TokenType::PlusSign
+                | TokenType::MinuxSign
+                | TokenType::VerticalLine
+                | TokenType::RightwardsArrow => {}
+                ref r#type if r#type.is_type_word() => {}
+                _ => return false,
+            }
+            if depth == 0 {
+                return self.tokens.peek_nth(n + 1).r#type == TokenType::LeftParenthesis;
+            }
+        }
+        false
+    }
+
+    // Parses the index after the `.` of a tuple field access, whose name is
+    // the index's digits. The lexer reads `pair.0.1` as `pair`, `.` and the
+    // decimal `0.1`, so a decimal is split back into two indices.
+    fn parse_tuple_indices(
+        &mut self,
+        base: ast::Expr,
+        start: &Position,
+    ) -> Result<ast::Expr, ParseError> {
+        let lexeme = self.tokens.peek().lexeme();
+        let indices: Vec<&str> = match self.tokens.peek().r#type {
+            TokenType::Integer { suffix: None, .. } => vec![lexeme],
+            TokenType::Decimal { suffix: None, .. } => lexeme.split('.').collect(),
+            _ => Vec::new(),
+        };
+        let is_index =
+            |index: &&str| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit());
+        if indices.is_empty() || indices.len() > 2 || !indices.iter().all(is_index) {
+            return Err(self.error(ParseErrorKind::InvalidTupleIndex));
+        }
+
+        let indices: Vec<String> = indices.into_iter().map(String::from).collect();
+        let token = self.bump();
+        let mut expr = base;
+        let mut column = token.span.start.column;
+        for index in indices {
+            let end = column + index.len() as u32;
+            let name = ast::Ident {
+                span: Span {
+                    start: Position {
+                        row: token.span.start.row,
+                        column,
+                    },
+                    end: Position {
+                        row: token.span.start.row,
+                        column: end,
+                    },
+                },
+                name: index,
+            };
+            expr = ast::Expr {
+                span: Span {
+                    start: start.clone(),
+                    end: name.span.end.clone(),
+                },
+                kind: ast::ExprKind::Field {
+                    base: Box::new(expr),
+                    name,
+                },
+            };
+            // Past the `.` between the two indices.
+            column = end + 1;
+        }
+        Ok(expr)
+    }
+
+    // arguments ::= '(' [expression {',' expression} [',']] ')'
+    fn parse_arguments(&mut self) -> Result<Vec<ast::Expr>, ParseError> {
+        let open = self.expect(TokenType::LeftParenthesis)?;
+        let mut args = Vec::new();
+        while !self.at(&TokenType::RightParenthesis) {
+            args.push(self.with_struct_literals(true, Self::parse_expr)?);
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect_closing(TokenType::RightParenthesis, open)?;
+        Ok(args)
+    }
+
+    // primary ::= literal | path | struct-literal | macro-call | parenthesized-expression
+    //           | block | if-expression | match-expression | interpolated-string
+    fn parse_primary(&mut self) -> Result<ast::Expr, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let kind = match &self.tokens.peek().r#type {
+            TokenType::Identifier(_) | TokenType::LowerSelf | TokenType::UpperSelf => {
+                let path = self.parse_expr_path()?;
+                if self.struct_literals && self.at(&TokenType::LeftCurlyBracket) {
+                    self.in_context("in struct literal", |parser| {
+                        parser.parse_struct_literal(path)
+                    })?
+                } else {
+                    ast::ExprKind::Path(path)
+                }
+            }
+            TokenType::MacroIdentifier(_) => {
+                ast::ExprKind::MacroCall(self.in_context("in macro call", Self::parse_macro_call)?)
+            }
+            TokenType::LeftCurlyBracket => {
+                ast::ExprKind::Block(self.with_struct_literals(true, Self::parse_block)?)
+            }
+            TokenType::LeftParenthesis => self.with_struct_literals(true, |parser| {
+                parser.in_context("in parentheses", Self::parse_parenthesized_expr)
+            })?,
+            TokenType::If => self.parse_if()?,
+            TokenType::Match => self.parse_match()?,
+            TokenType::InterpolatedString(_) => self.parse_interpolated_string()?,
+            r#type if r#type.is_item_modifier() => {
+                return Err(self.error(ParseErrorKind::VisibilityNotAllowed("expression")))
+            }
+            _ => ast::ExprKind::Literal(self.parse_literal(ParseErrorKind::ExpectedExpression)?),
+        };
+        Ok(ast::Expr {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // Whether the next token is a sign that belongs to the integer or decimal
+    // after it.
+    fn at_signed_number(&mut self) -> bool {
+        matches!(
+            self.tokens.peek().r#type,
+            TokenType::MinuxSign | TokenType::PlusSign
+        ) && matches!(
+            self.tokens.peek_nth(1).r#type,
+            TokenType::Integer { .. } | TokenType::Decimal { .. }
+        )
+    }
+
+    // literal ::= ['-' | '+'] (integer | decimal) | bits | boolean | string
+    //           | character | 'null'
+    //
+    // The sign is applied before the number is range-checked, so that
+    // `-9223372036854775808` fits an `int64`. `otherwise` is the error for
+    // when there's no literal.
+    fn parse_literal(&mut self, otherwise: ParseErrorKind) -> Result<ast::Literal, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let sign = if self.at_signed_number() {
+            Some(self.bump())
+        } else {
+            None
+        };
+        let negated = sign
+            .as_ref()
+            .is_some_and(|sign| sign.r#type == TokenType::MinuxSign);
+
+        let token = self.tokens.peek();
+        let literal = literal(&token.r#type).ok_or_else(|| self.error(otherwise))?;
+        let literal = match literal {
+            // Only `9223372036854775808` comes from the lexer as `i64::MIN`
+            // without a `-` of its own, and it's only in range negated.
+            ast::Literal::Integer { value, .. }
+                if value == i64::MIN && !negated && !token.lexeme().starts_with('-') =>
+            {
+                let sign = sign.as_ref().map_or("", |sign| sign.lexeme());
+                let literal = format!("{sign}{}", token.lexeme());
+                self.bump();
+                return Err(ParseError {
+                    kind: ParseErrorKind::LiteralOutOfRange {
+                        literal,
+                        target: "int64",
+                    },
+                    span: self.span_from(start),
+                });
+            }
+            ast::Literal::Integer { value, suffix } if negated => ast::Literal::Integer {
+                value: value.wrapping_neg(),
+                suffix,
+            },
+            ast::Literal::Decimal { value, suffix } if negated => ast::Literal::Decimal {
+                value: -value,
+                suffix,
+            },
+            literal => literal,
+        };
+        self.bump();
+        Ok(literal)
+    }
+
+    // The code of each `\(...)` in the string is parsed as one expression.
+    fn parse_interpolated_string(&mut self) -> Result<ast::ExprKind, ParseError> {
+        let TokenType::InterpolatedString(parts) = &self.tokens.peek().r#type else {
+            return Err(self.error(ParseErrorKind::ExpectedExpression));
+        };
+        let parts = parts.clone();
+        let string = self.bump();
+
+        let mut parsed = Vec::new();
+        for part in parts {
+            parsed.push(match part {
+                StringPart::Literal(text) => ast::StringPart::Literal(text.into_owned()),
+                StringPart::Tokens(tokens) => {
+                    let end = tokens
+                        .last()
+                        .map_or(string.span.start.clone(), |last| last.span.end.clone());
+                    ast::StringPart::Expr(self.parse_interpolation(tokens, end)?)
+                }
+            });
+        }
+        Ok(ast::ExprKind::InterpolatedString(parsed))
+    }
+
+    // Parses the tokens of an interpolation, which end at `end`, with a
+    // parser of their own whose errors are added to this one's.
+    fn parse_interpolation(
+        &mut self,
+        tokens: Vec<Token<'a>>,
+        end: Position,
+    ) -> Result<ast::Expr, ParseError> {
+        let mut parser = Parser {
+            tokens: TokenStream::from_tokens(tokens, end),
+            context: "in string interpolation",
+            struct_literals: true,
+            unclosed_at_end: false,
+            errors: Vec::new(),
+        };
+        let result = parser.parse_expr().and_then(|expr| {
+            if parser.at(&TokenType::EndOfFile) {
+                Ok(expr)
+            } else {
+                Err(parser.expected(&[TokenType::RightParenthesis]))
+            }
+        });
+        self.errors.append(&mut parser.errors);
+        result
+    }
+
+    // if-expression ::= 'if' expression block ['else' (if-expression | block)]
+    //
+    // The condition needs no parentheses, so a struct literal can't be written
+    // in it without them.
+    fn parse_if(&mut self) -> Result<ast::ExprKind, ParseError> {
+        self.expect(TokenType::If)?;
+        let condition = self.with_struct_literals(false, Self::parse_expr)?;
+        let then_block = self.parse_body("in if expression")?;
+        let else_branch = match self.eat(&TokenType::Else) {
+            Some(_) => {
+                let start = self.tokens.peek().span.start.clone();
+                let kind = match self.tokens.peek().r#type {
+                    TokenType::If => self.parse_if()?,
+                    _ => ast::ExprKind::Block(self.parse_body("in if expression")?),
+                };
+                Some(Box::new(ast::Expr {
+                    kind,
+                    span: self.span_from(start),
+                }))
+            }
+            None => None,
+        };
+        Ok(ast::ExprKind::If {
+            condition: Box::new(condition),
+            then_block,
+            else_branch,
+        })
+    }
+
+    // The block of an `if` or a loop, which `context` says is missing if
+    // there's no `{`.
+    fn parse_body(&mut self, context: &'static str) -> Result<ast::Block, ParseError> {
+        if !self.at(&TokenType::LeftCurlyBracket) {
+            return Err(self.in_context(context, |parser| {
+                parser.expected(&[TokenType::LeftCurlyBracket])
+            }));
+        }
+        self.with_struct_literals(true, Self::parse_block)
+    }
+
+    // match-expression ::= 'match' expression '{' [match-arm {',' match-arm} [',']] '}'
+    // match-arm ::= pattern '=>' expression
+    fn parse_match(&mut self) -> Result<ast::ExprKind, ParseError> {
+        self.expect(TokenType::Match)?;
+        let scrutinee = self.with_struct_literals(false, Self::parse_expr)?;
+        let open = self.in_context("in match expression", |parser| {
+            parser.expect(TokenType::LeftCurlyBracket)
+        })?;
+        let mut arms = Vec::new();
+        while !self.at(&TokenType::RightCurlyBracket) {
+            let start = self.tokens.peek().span.start.clone();
+            let pattern = self.parse_pattern()?;
+            self.in_context("in match arm", |parser| {
+                parser.expect(TokenType::RightwardsDoubleArrow)
+            })?;
+            let body = self.with_struct_literals(true, Self::parse_expr)?;
+            arms.push(ast::MatchArm {
+                pattern,
+                body,
+                span: self.span_from(start),
+            });
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        if !self.at(&TokenType::RightCurlyBracket) && !self.at(&TokenType::EndOfFile) {
+            return Err(self.in_context("in match expression", |parser| {
+                parser.expected(&[TokenType::Comma, TokenType::RightCurlyBracket])
+            }));
+        }
+        self.expect_closing(TokenType::RightCurlyBracket, open)?;
+        Ok(ast::ExprKind::Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+        })
+    }
+
+    // pattern ::= literal | identifier | '_' | identifier ':' pattern
+    //           | '(' [pattern {',' pattern} [',']] ')'
+    fn parse_pattern(&mut self) -> Result<ast::Pattern, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let kind = match &self.tokens.peek().r#type {
+            TokenType::Underscore => {
+                self.bump();
+                ast::PatternKind::Wildcard
+            }
+            TokenType::Identifier(_) => {
+                let name = self.expect_identifier()?;
+                if self.eat(&TokenType::Colon).is_some() {
+                    ast::PatternKind::Variant {
+                        name,
+                        pattern: Box::new(self.parse_pattern()?),
+                    }
+                } else {
+                    ast::PatternKind::Binding(name)
+                }
+            }
+            TokenType::LeftParenthesis => {
+                let open = self.bump();
+                let mut elements = Vec::new();
+                let mut trailing_comma = false;
+                while !self.at(&TokenType::RightParenthesis) {
+                    elements.push(self.parse_pattern()?);
+                    trailing_comma = self.eat(&TokenType::Comma).is_some();
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.expect_closing(TokenType::RightParenthesis, open)?;
+                // Like in an expression, parentheses around a single pattern
+                // only group it.
+                if elements.len() == 1 && !trailing_comma {
+                    return Ok(elements.pop().unwrap());
+                }
+                ast::PatternKind::Tuple(elements)
+            }
+            _ => ast::PatternKind::Literal(self.parse_literal(ParseErrorKind::ExpectedPattern)?),
+        };
+        Ok(ast::Pattern {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // A path that can start with `self` or `Self`, as in `Self::create`.
+    fn parse_expr_path(&mut self) -> Result<ast::Path, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let first = match self.tokens.peek().r#type {
+            TokenType::LowerSelf | TokenType::UpperSelf => {
+                let token = self.bump();
+                ast::Ident {
+                    name: token.lexeme().to_string(),
+                    span: token.span,
+                }
+            }
+            _ => self.expect_identifier()?,
+        };
+        let mut segments = vec![first];
+        while self.eat(&TokenType::DoubleColon).is_some() {
+            segments.push(self.expect_identifier()?);
+        }
+        Ok(ast::Path {
+            segments,
+            span: self.span_from(start),
+        })
+    }
+
+    // struct-literal ::= path '{' [field-init {',' field-init} [',']] '}'
+    // field-init ::= identifier [':' expression]
+    //
+    // Like in Rust, a path followed by `{` always starts a struct literal,
+    // even at the start of a statement.
+    fn parse_struct_literal(&mut self, path: ast::Path) -> Result<ast::ExprKind, ParseError> {
+        let ty = ast::TypeExpr {
+            span: path.span.clone(),
+            kind: ast::TypeExprKind::Named {
+                path,
+                args: Vec::new(),
+            },
+        };
+        let open = self.expect(TokenType::LeftCurlyBracket)?;
+        let mut fields = Vec::new();
+        while !self.at(&TokenType::RightCurlyBracket) {
+            let name = self.expect_identifier()?;
+            fields.push(if self.at(&TokenType::Colon) {
+                self.parse_field_value(name)?
+            } else {
+                // The shorthand `Self { field1 }` for `Self { field1: field1 }`.
+                ast::FieldValue {
+                    span: name.span.clone(),
+                    value: ast::Expr {
+                        span: name.span.clone(),
+                        kind: ast::ExprKind::Path(ast::Path {
+                            span: name.span.clone(),
+                            segments: vec![name.clone()],
+                        }),
+                    },
+                    name,
+                }
+            });
+            if self.eat(&TokenType::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect_closing(TokenType::RightCurlyBracket, open)?;
+        Ok(ast::ExprKind::Struct { ty, fields })
+    }
+
+    // parenthesized-expression ::= '(' ')'
+    //                            | '(' expression ')'
+    //                            | '(' expression ',' [expression {',' expression} [',']] ')'
+    //                            | '(' field-value {',' field-value} [','] ')'
+    // field-value ::= identifier ':' expression
+    fn parse_parenthesized_expr(&mut self) -> Result<ast::ExprKind, ParseError> {
+        let open = self.expect(TokenType::LeftParenthesis)?;
+        if self.eat(&TokenType::RightParenthesis).is_some() {
+            return Ok(ast::ExprKind::Tuple(Vec::new()));
+        }
+
+        let first = self.parse_expr()?;
+        let kind = match self.tokens.peek().r#type {
+            // Like with tagged tuple types, the name is parsed as an
+            // expression first and only turns out to be a name at the `:`.
+            TokenType::Colon => {
+                let name = single_segment(first)?;
+                let mut fields = vec![self.parse_field_value(name)?];
+                while self.eat(&TokenType::Comma).is_some() {
+                    if self.at(&TokenType::RightParenthesis) {
+                        break;
+                    }
+                    let name = self.expect_identifier()?;
+                    fields.push(self.parse_field_value(name)?);
+                }
+                ast::ExprKind::TaggedTuple(fields)
+            }
+            TokenType::Comma => {
+                let mut elements = vec![first];
+                while self.eat(&TokenType::Comma).is_some() {
+                    if self.at(&TokenType::RightParenthesis) {
+                        break;
+                    }
+                    elements.push(self.parse_expr()?);
+                }
+                ast::ExprKind::Tuple(elements)
+            }
+            _ => first.kind,
+        };
+        self.expect_closing(TokenType::RightParenthesis, open)?;
+        Ok(kind)
+    }
+
+    // Parses the rest of a field value after its name.
+    fn parse_field_value(&mut self, name: ast::Ident) -> Result<ast::FieldValue, ParseError> {
+        self.expect(TokenType::Colon)?;
+        let value = self.parse_expr()?;
+        Ok(ast::FieldValue {
+            span: Span {
+                start: name.span.start.clone(),
+                end: value.span.end.clone(),
+            },
+            name,
+            value,
+        })
+    }
+
+    // path ::= identifier {'::' identifier}
+    fn parse_path(&mut self) -> Result<ast::Path, ParseError> {
+        let start = self.tokens.peek().span.start.clone();
+        let mut segments = vec![self.expect_identifier()?];
+        while self.eat(&TokenType::DoubleColon).is_some() {
+            segments.push(self.expect_identifier()?);
+        }
+        Ok(ast::Path {
+            segments,
+            span: self.span_from(start),
+        })
+    }
+
+    fn expect_identifier(&mut self) -> Result<ast::Ident, ParseError> {
+        let TokenType::Identifier(name) = &self.tokens.peek().r#type else {
+            let kind = match self.tokens.peek().r#type.keyword_str() {
+                Some(keyword) => ParseErrorKind::KeywordAsIdentifier(keyword),
+                None => ParseErrorKind::ExpectedIdentifier,
+            };
+            return Err(self.error(kind));
+        };
+
+        let name = name.to_string();
+        let token = self.bump();
+        Ok(ast::Ident {
+            name,
+            span: token.span,
+        })
+    }
+
+    // Bit fields are often named after their width, like `bit1`, so a type
+    // word other than `Self` names a field as well as an identifier does.
+    fn expect_field_name(&mut self) -> Result<ast::Ident, ParseError> {
+        let r#type = &self.tokens.peek().r#type;
+        if !r#type.is_type_word() || *r#type == TokenType::UpperSelf {
+            return self.expect_identifier();
+        }
+        let token = self.bump();
+        Ok(ast::Ident {
+            name: token.lexeme().to_string(),
+            span: token.span,
+        })
+    }
+
+    // Reports `error` unless one was already reported at the same token, as
+    // when the `;` missing before it left the item or statement looking done.
+    fn report(&mut self, error: ParseError) {
+        if self
+            .errors
+            .last()
+            .is_some_and(|last| last.span == error.span)
+        {
+            return;
+        }
+        self.errors.push(error);
+    }
+
+    // What comes before a `;` is enough to go on with, so a missing one is only
+    // reported.
+    fn expect_semicolon(&mut self) {
+        if let Err(error) = self.expect(TokenType::Semicolon) {
+            self.errors.push(error);
+        }
+    }
+
+    // Expects the bracket that closes `open`, blaming `open` if it's missing
+    // since that's where the mistake most likely is.
+    fn expect_closing(
+        &mut self,
+        r#type: TokenType<'static>,
+        open: Token<'a>,
+    ) -> Result<Token<'a>, ParseError> {
+        let closing = self.eat(&r#type);
+        if closing.is_none() && self.at(&TokenType::EndOfFile) {
+            self.unclosed_at_end = true;
+        }
+        closing.ok_or(ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: open.span,
+        })
+    }
+
+    // Expects the `>` closing `open`, taking the first half of a `>>` that
+    // closes two lists at once.
+    fn expect_right_angle(&mut self, open: Token<'a>) -> Result<Token<'a>, ParseError> {
+        if let Some(first) = self.tokens.split_double_right_angle() {
+            return Ok(first);
+        }
+        self.expect_closing(TokenType::RightAngleBracket, open)
+    }
+
+    fn expect(&mut self, r#type: TokenType<'static>) -> Result<Token<'a>, ParseError> {
+        self.expect_one_of(&[r#type])
+    }
+
+    fn expect_one_of(&mut self, types: &[TokenType<'static>]) -> Result<Token<'a>, ParseError> {
+        self.tokens.expect(types, self.context)
+    }
+
+    // An error for the current token not being one of `types`.
+    fn expected(&self, types: &[TokenType<'static>]) -> ParseError {
+        self.error(ParseErrorKind::Expected {
+            expected: types.iter().cloned().map(TokenDescription::Token).collect(),
+            found: TokenDescription::of(self.tokens.peek()),
+            context: self.context,
+        })
+    }
+
+    // Runs `parse` with `context` as where the parser is.
+    fn in_context<T>(&mut self, context: &'static str, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.context, context);
+        let result = parse(self);
+        self.context = outer;
+        result
+    }
+
+    // Runs `parse` with struct literals allowed or not, restoring whether they
+    // were afterwards.
+    fn with_struct_literals<T>(&mut self, allowed: bool, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = outer;
+        result
+    }
+
+    fn eat(&mut self, r#type: &TokenType) -> Option<Token<'a>> {
+        self.at(r#type).then(|| self.bump())
+    }
+
+    fn at(&self, r#type: &TokenType) -> bool {
+        self.tokens.peek().r#type == *r#type
+    }
+
+    // Moves on to the next token, returning the one that was current.
+    fn bump(&mut self) -> Token<'a> {
+        self.tokens.next()
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            span: self.tokens.peek().span.clone(),
+        }
+    }
+
+    // The span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Position) -> Span {
+        Span {
+            start,
+            end: self.tokens.previous_end().clone(),
+        }
+    }
+}
+
+// Whether a token is the keyword of an item, where parsing can start again
+// after an error.
+fn starts_item(r#type: &TokenType) -> bool {
+    matches!(
+        r#type,
+        TokenType::Module
+            | TokenType::Use
+            | TokenType::Type
+            | TokenType::Trait
+            | TokenType::Extend
+            | TokenType::Function
+            | TokenType::Value
+            | TokenType::Alias
+    )
+}
+
+fn starts_type(r#type: &TokenType) -> bool {
+    r#type.is_type_word()
+        || matches!(
+            r#type,
+            TokenType::Identifier(_) | TokenType::LeftParenthesis | TokenType::ComercialAt
+        )
+}
+
+// The parameters of a function type from the type parsed before its arrow,
+// where a tagged tuple gives them names.
+fn parameters(r#type: ast::TypeExpr) -> Vec<ast::Param> {
+    let unnamed = |ty: ast::TypeExpr| ast::Param {
+        name: None,
+        span: ty.span.clone(),
+        ty,
+        default: None,
+    };
+    match r#type.kind {
+        ast::TypeExprKind::Tuple(elements) => elements.into_iter().map(unnamed).collect(),
+        ast::TypeExprKind::TaggedTuple(fields) => fields
+            .into_iter()
+            .map(|field| ast::Param {
+                name: Some(field.name),
+                ty: field.ty,
+                default: None,
+                span: field.span,
+            })
+            .collect(),
+        _ => vec![unnamed(r#type)],
+    }
+}
+
+fn is_composite_separator(r#type: &TokenType) -> bool {
+    matches!(
+        r#type,
+        TokenType::Comma | TokenType::VerticalLine | TokenType::Asterisk | TokenType::PlusSign
+    )
+}
+
+// The precedence of the comparison operators, which don't associate.
+const COMPARISON: u8 = 3;
+
+// The precedence of the range operators, which bind looser than any other
+// and don't associate either, so `0..n + 1` ends at `n + 1`.
+const RANGE: u8 = 0;
+
+// The operator a token stands for between two operands, and how tightly it
+// binds.
+fn binary_operator(r#type: &TokenType) -> Option<(ast::BinaryOp, u8)> {
+    let operator = match r#type {
+        TokenType::DoubleFullStop => (ast::BinaryOp::Range, RANGE),
+        TokenType::DoubleFullStopEquals => (ast::BinaryOp::RangeInclusive, RANGE),
+        TokenType::DoubleVerticalLine => (ast::BinaryOp::Or, 1),
+        TokenType::DoubleAmpersand => (ast::BinaryOp::And, 2),
+        TokenType::DoubleEquals => (ast::BinaryOp::Equal, COMPARISON),
+        TokenType::NotEquals => (ast::BinaryOp::NotEqual, COMPARISON),
+        TokenType::LeftAngleBracket => (ast::BinaryOp::Less, COMPARISON),
+        TokenType::RightAngleBracket => (ast::BinaryOp::Greater, COMPARISON),
+        TokenType::LessThanOrEqual => (ast::BinaryOp::LessEqual, COMPARISON),
+        TokenType::GreaterThanOrEqual => (ast::BinaryOp::GreaterEqual, COMPARISON),
+        TokenType::VerticalLine => (ast::BinaryOp::BitOr, 4),
+        TokenType::CircumflexAccent => (ast::BinaryOp::BitXor, 5),
+        TokenType::Ampersand => (ast::BinaryOp::BitAnd, 6),
+        TokenType::DoubleLeftAngle => (ast::BinaryOp::ShiftLeft, 7),
+        TokenType::DoubleRightAngle => (ast::BinaryOp::ShiftRight, 7),
+        TokenType::PlusSign => (ast::BinaryOp::Add, 8),
+        TokenType::MinuxSign => (ast::BinaryOp::Subtract, 8),
+        TokenType::Asterisk => (ast::BinaryOp::Multiply, 9),
+        TokenType::Solidus => (ast::BinaryOp::Divide, 9),
+        TokenType::PercentSign => (ast::BinaryOp::Remainder, 9),
+        _ => return None,
+    };
+    Some(operator)
+}
+
+// Whether an expression names somewhere that can be assigned to.
+fn is_place(expr: &ast::Expr) -> bool {
+    matches!(
+        expr.kind,
+        ast::ExprKind::Path(_)
+            | ast::ExprKind::Field { .. }
+            | ast::ExprKind::Unary {
+                op: ast::UnaryOp::Dereference,
+                ..
+            }
+    )
+}
+
+fn literal(r#type: &TokenType) -> Option<ast::Literal> {
+    let literal = match r#type {
+        TokenType::Bits { value, suffix } => ast::Literal::Bits {
+            value: *value,
+            suffix: *suffix,
+        },
+        TokenType::Integer { value, suffix } => ast::Literal::Integer {
+            value: *value,
+            suffix: *suffix,
+        },
+        TokenType::Decimal { value, suffix } => ast::Literal::Decimal {
+            value: *value,
+            suffix: *suffix,
+        },
+        TokenType::Boolean(value) => ast::Literal::Boolean(*value),
+        TokenType::String(value) => ast::Literal::String(value.to_string()),
+        TokenType::Character { value, width } => ast::Literal::Character {
+            value: *value,
+            width: *width,
+        },
+        TokenType::Null => ast::Literal::Null,
+        _ => return None,
+    };
+    Some(literal)
+}
+
+// The name that starts an enumeration or a tagged tuple, which was parsed as a
+// type before the `|` or `:` after it showed what it was.
+fn single_name(r#type: ast::TypeExpr) -> Result<ast::Ident, ParseError> {
+    match r#type.kind {
+        ast::TypeExprKind::Named { mut path, args }
+            if path.segments.len() == 1 && args.is_empty() =>
+        {
+            Ok(path.segments.remove(0))
+        }
+        _ => Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: r#type.span,
+        }),
+    }
+}
+
+// Attaches attributes to the item they came before, which then starts at
+// the first of them.
+fn with_attributes(mut item: ast::Item, attributes: Vec<ast::MacroCall>) -> ast::Item {
+    if let Some(first) = attributes.first() {
+        item.span.start = first.span.start.clone();
+    }
+    item.attributes = attributes;
+    item
+}
+
+fn with_visibility(mut item: ast::Item, keyword: Option<Token>) -> ast::Item {
+    if let Some(keyword) = keyword {
+        item.span.start = keyword.span.start;
+        item.visibility = match keyword.r#type {
+            TokenType::Public => ast::Visibility::Public,
+            _ => ast::Visibility::Private,
+        };
+    }
+    item
+}
+
+fn dangling_attribute(attributes: &[ast::MacroCall]) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::DanglingAttribute,
+        span: attributes.last().unwrap().span.clone(),
+    }
+}
+
+// The expression-side `single_name`, for a name parsed as a path.
+fn single_segment(expr: ast::Expr) -> Result<ast::Ident, ParseError> {
+    match expr.kind {
+        ast::ExprKind::Path(mut path) if path.segments.len() == 1 => Ok(path.segments.remove(0)),
+        _ => Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: expr.span,
+        }),
+    }
+}
+
+#[cfg(test)]
+fn expected_token(
+    r#type: TokenType<'static>,
+    found: TokenDescription,
+    context: &'static str,
+) -> ParseErrorKind {
+    ParseErrorKind::Expected {
+        expected: vec![TokenDescription::Token(r#type)],
+        found,
+        context,
+    }
+}
+
+#[cfg(test)]
+fn described(kind: &'static str, text: &str) -> TokenDescription {
+    TokenDescription::Named(kind, text.to_string())
+}
+
+#[cfg(test)]
+fn identifier(name: &str) -> TokenType<'_> {
+    TokenType::Identifier(Cow::from(name))
+}
+
+#[cfg(test)]
+fn lex_types(source: &str) -> Vec<TokenType<'_>> {
+    Lexer::new(source.chars())
+        .map(|token| token.unwrap().r#type)
+        .filter(|r#type| *r#type != TokenType::EndOfFile)
+        .collect()
+}
+
+#[cfg(test)]
+fn span(start: (u32, u32), end: (u32, u32)) -> Span {
+    Span {
+        start: Position {
+            row: start.0,
+            column: start.1,
+        },
+        end: Position {
+            row: end.0,
+            column: end.1,
+        },
+    }
+}
+
+#[test]
+pub fn test_lexer() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lexing_test_file.xi");
+    let buffer = std::fs::read_to_string(&path).unwrap();
+
+    let tokens = vec![
+        // TokenType::Bits { value: u64, suffix: None },
+        // TokenType::Integer { value: i64, suffix: None },
+        // TokenType::Decimal { value: f64, suffix: None },
+        // TokenType::Boolean(bool),
+        TokenType::PlusSign,
+        TokenType::Integer {
+            value: 21,
+            suffix: None,
+        },
+        TokenType::MinuxSign,
+        TokenType::Integer {
+            value: 21,
+            suffix: None,
+        },
+        TokenType::Decimal {
+            value: 21.21,
+            suffix: None,
+        },
+        TokenType::Integer {
+            value: 21,
+            suffix: None,
+        },
+        TokenType::Boolean(true),
+        TokenType::Boolean(false),
+        TokenType::Null,
+        TokenType::Identifier(Cow::from("C_oolIdentifier32_")),
+        // `''` is an error, not a token.
+        TokenType::String(Cow::from("")),
+        TokenType::Character {
+            value: '\'',
+            width: None,
+        },
+        TokenType::String(Cow::from("Hello,\'\" World!")),
+        TokenType::Module,
+        TokenType::Trait,
+        TokenType::Type,
+        TokenType::Extend,
+        TokenType::Function,
+        TokenType::Value,
+        TokenType::Alias,
+        TokenType::Use,
+        TokenType::Return,
+        TokenType::As,
+        TokenType::If,
+        TokenType::Else,
+        TokenType::Match,
+        TokenType::While,
+        TokenType::For,
+        TokenType::In,
+        TokenType::Loop,
+        TokenType::Break,
+        TokenType::Continue,
+        TokenType::Public,
+        TokenType::Private,
+        TokenType::Mutable,
+        TokenType::Constant,
+        TokenType::LowerSelf,
+        TokenType::UpperSelf,
+        TokenType::Bit,
+        TokenType::Bit8,
+        TokenType::Bit16,
+        TokenType::Bit32,
+        TokenType::Bit64,
+        TokenType::Int,
+        TokenType::Int8,
+        TokenType::Int16,
+        TokenType::Int32,
+        TokenType::Int64,
+        TokenType::Float,
+        TokenType::Float8,
+        TokenType::Float16,
+        TokenType::Float32,
+        TokenType::Float64,
+        TokenType::Bool,
+        TokenType::Char,
+        TokenType::Char8,
+        TokenType::Char16,
+        TokenType::Char32,
+        TokenType::FullStop,
+        TokenType::Comma,
+        TokenType::Colon,
+        TokenType::Semicolon,
+        TokenType::EqualsSign,
+        TokenType::PlusSign,
+        TokenType::MinuxSign,
+        TokenType::Asterisk,
+        TokenType::Solidus,
+        TokenType::ReverseSolidus,
+        TokenType::VerticalLine,
+        TokenType::ExclamationMark,
+        TokenType::QuestionMark,
+        TokenType::ComercialAt,
+        TokenType::NumberSign,
+        TokenType::PercentSign,
+        TokenType::CircumflexAccent,
+        TokenType::Tilde,
+        TokenType::DollarSign,
+        TokenType::RightwardsArrow,
+        TokenType::LeftCurlyBracket,
+        TokenType::RightCurlyBracket,
+        TokenType::LeftParenthesis,
+        TokenType::RightParenthesis,
+        TokenType::LeftAngleBracket,
+        TokenType::RightAngleBracket,
+        TokenType::LeftSquareBracket,
+        TokenType::RightSquareBracket,
+        TokenType::EndOfFile,
+    ];
+
+    let span = |row, start, end| Span {
+        start: Position { row, column: start },
+        end: Position { row, column: end },
+    };
+    let expected_spans = vec![
+        span(8, 1, 19),
+        span(10, 1, 3),
+        span(12, 1, 20),
+        span(77, 1, 3),
+    ];
+    let mut spans = Vec::new();
+
+    let empty_character = LexingError {
+        kind: LexingErrorKind::EmptyCharacter,
+        position: Position { row: 9, column: 1 },
+        end: Position { row: 9, column: 3 },
+    };
+    let mut lexer = Lexer::from_str(&buffer);
+    for token in tokens.clone() {
+        let mut next = lexer.next();
+        if next == Some(Err(empty_character.clone())) {
+            next = lexer.next();
+        }
+        if let Some(result) = next {
+            if let Ok(result) = result {
+                println!("Expected: {:?}, Recieved: {:?}", token, result.r#type);
+                if result.r#type != token {
+                    panic!();
+                }
+                if let TokenType::Identifier(_)
+                | TokenType::String(_)
+                | TokenType::RightwardsArrow = result.r#type
+                {
+                    spans.push(result.span().clone());
+                }
+                match result.r#type {
+                    TokenType::Decimal { .. } => assert_eq!(result.lexeme(), "21.21"),
+                    TokenType::RightwardsArrow => assert_eq!(result.lexeme(), "->"),
+                    TokenType::EndOfFile => {
+                        assert_eq!(*result.position(), Position { row: 86, column: 1 })
+                    }
+                    _ => {}
+                }
+            } else {
+                println!("{:?} {:?}", lexer.position(), result);
+                panic!();
+            }
+        } else {
+            break;
+        }
+    }
+    assert_eq!(spans, expected_spans);
+    assert!(lexer.next().is_none());
+
+    let mut source = String::new();
+    let (lexed, errors) = lex_file(&path, &mut source).unwrap();
+    assert_eq!(
+        errors,
+        [FileLexingError {
+            path: path.clone(),
+            error: empty_character,
+        }]
+    );
+    assert_eq!(
+        lexed
+            .into_iter()
+            .map(|token| token.r#type)
+            .collect::<Vec<_>>(),
+        tokens
+    );
+}
+
+#[test]
+pub fn test_lex_file() {
+    let path = std::env::temp_dir().join(format!("xic_lex_file_{}.xi", std::process::id()));
+    std::fs::write(&path, "a\n ` b").unwrap();
+    let mut source = String::new();
+    let (tokens, errors) = lex_file(&path, &mut source).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(
+        errors,
+        vec![FileLexingError {
+            path: path.clone(),
+            error: LexingError {
+                kind: LexingErrorKind::UnknownToken,
+                position: Position { row: 2, column: 2 },
+                end: Position { row: 2, column: 3 },
+            },
+        }]
+    );
+    assert_eq!(
+        lex_file(&path, &mut String::new()).unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+}
+
+#[test]
+pub fn test_block_comments() {
+    let source = "a #[ \"not a string' #[ nested ]# 'x' ]# b\n#[\nrow\n]# c";
+    let mut lexer = Lexer::new(source.chars());
+
+    let a = lexer.next().unwrap().unwrap();
+    assert_eq!(a.r#type, TokenType::Identifier(Cow::from("a")));
+
+    let b = lexer.next().unwrap().unwrap();
+    assert_eq!(b.r#type, TokenType::Identifier(Cow::from("b")));
+
+    let c = lexer.next().unwrap().unwrap();
+    assert_eq!(c.r#type, TokenType::Identifier(Cow::from("c")));
+    assert_eq!(*c.position(), Position { row: 4, column: 4 });
+
+    let end = lexer.next().unwrap().unwrap();
+    assert_eq!(end.r#type, TokenType::EndOfFile);
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+pub fn test_unterminated_block_comment() {
+    let mut lexer = Lexer::new("a\n  #[ #[ ]# \"".chars());
+    lexer.next().unwrap().unwrap();
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err(),
+        LexingError {
+            kind: LexingErrorKind::UnterminatedComment,
+            position: Position { row: 2, column: 3 },
+            end: Position { row: 2, column: 13 },
+        }
+    );
+}
+
+#[test]
+pub fn test_hexadecimal_literals() {
+    let mut lexer = Lexer::new("0x0 0xDEADBEEF 0XdeadBeef 0xFF_FF".chars());
+    for expected in [0, 0xDEADBEEF, 0xDEADBEEF, 0xFFFF] {
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::Bits {
+                value: expected,
+                suffix: None
+            }
+        );
+    }
+
+    let mut lexer = Lexer::new("0x10000000000000000".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::LiteralOutOfRange {
+            literal: String::from("0x10000000000000000"),
+            target: "bit64",
+        }
+    );
+
+    let mut lexer = Lexer::new("0x".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::BitsParsing
+    );
+    assert_eq!(*lexer.position(), Position { row: 1, column: 3 });
+}
+
+#[test]
+pub fn test_binary_literals() {
+    let source = format!("0b0 0B101 0b{}", "1".repeat(64));
+    let mut lexer = Lexer::new(source.chars());
+    for expected in [0, 0b101, u64::MAX] {
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::Bits {
+                value: expected,
+                suffix: None
+            }
+        );
+    }
+
+    let mut lexer = Lexer::new("0b".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::BitsParsing
+    );
+
+    let mut lexer = Lexer::new("0b2".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::InvalidDigit
+    );
+}
+
+#[test]
+pub fn test_octal_literals() {
+    let mut lexer = Lexer::new("0o0 0O777 0o00".chars());
+    for expected in [0, 0o777, 0] {
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::Bits {
+                value: expected,
+                suffix: None
+            }
+        );
+    }
+
+    let mut lexer = Lexer::new("0o17777777777777777777777".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::LiteralOutOfRange {
+            literal: String::from("0o17777777777777777777777"),
+            target: "bit64",
+        }
+    );
+
+    let mut lexer = Lexer::new("0o".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::BitsParsing
+    );
+
+    let mut lexer = Lexer::new("0o78".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::InvalidDigit
+    );
+}
+
+#[test]
+pub fn test_numeric_separators() {
+    let mut lexer = Lexer::from_str("1_000_000 0xFF_FF -1_0 1_234.5_6 ").with_signed_literals();
+    let expected = [
+        TokenType::Integer {
+            value: 1_000_000,
+            suffix: None,
+        },
+        TokenType::Bits {
+            value: 0xFFFF,
+            suffix: None,
+        },
+        TokenType::Integer {
+            value: -10,
+            suffix: None,
+        },
+        TokenType::Decimal {
+            value: 1_234.56,
+            suffix: None,
+        },
+    ];
+    for token in expected {
+        assert_eq!(lexer.next().unwrap().unwrap().r#type, token);
+    }
+
+    for source in ["1__0", "1_", "1_.5", "1.5_", "0x_1", "0xF_"] {
+        let mut lexer = Lexer::new(source.chars());
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind,
+            LexingErrorKind::MalformedNumericSeparator
+        );
+    }
+}
+
+#[test]
+pub fn test_exponent_literals() {
+    let sources = ["1e9", "2.5e-3", "1E+10", "-4.2e2", "1_0e1_0"];
+    let source = sources.join(" ");
+    let mut lexer = Lexer::from_str(&source).with_signed_literals();
+    for source in sources {
+        let expected = source.replace('_', "").parse::<f64>().unwrap();
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::Decimal {
+                value: expected,
+                suffix: None
+            }
+        );
+    }
+
+    for source in ["1e", "1e+", "1e-x", "2.5E "] {
+        let mut lexer = Lexer::new(source.chars());
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind,
+            LexingErrorKind::DecimalParsing
+        );
+    }
+}
+
+#[test]
+pub fn test_escape_sequences() {
+    let mut lexer = Lexer::new(r#"'\t' '\0' "line1\nline2\r\t\0""#.chars());
+    let expected = [
+        TokenType::Character {
+            value: '\t',
+            width: None,
+        },
+        TokenType::Character {
+            value: '\0',
+            width: None,
+        },
+        TokenType::String(Cow::from("line1\nline2\r\t\0")),
+    ];
+    for token in expected {
+        assert_eq!(lexer.next().unwrap().unwrap().r#type, token);
+    }
+
+    let mut lexer = Lexer::new(r#""\q""#.chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::InvalidEscapeSequence
+    );
+
+    // The rest of a literal with a bad escape is skipped, so what follows it
+    // is lexed as usual.
+    let types: Vec<_> = Lexer::new(r#"value s = "abc\q def" x y; '\q' z"#.chars())
+        .map(|token| token.map(|token| token.r#type).map_err(|error| error.kind))
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            Ok(TokenType::Value),
+            Ok(TokenType::Identifier(Cow::from("s"))),
+            Ok(TokenType::EqualsSign),
+            Err(LexingErrorKind::InvalidEscapeSequence),
+            Ok(TokenType::Identifier(Cow::from("x"))),
+            Ok(TokenType::Identifier(Cow::from("y"))),
+            Ok(TokenType::Semicolon),
+            Err(LexingErrorKind::InvalidEscapeSequence),
+            Ok(TokenType::Identifier(Cow::from("z"))),
+            Ok(TokenType::EndOfFile),
+        ]
+    );
+}
+
+#[test]
+pub fn test_unicode_escapes() {
+    let mut lexer = Lexer::new(r#""\u{1F600}\u{e9}" '\u{20AC}' x"#.chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::String(Cow::from("\u{1F600}\u{e9}"))
+    );
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::Character {
+            value: '\u{20AC}',
+            width: None
+        }
+    );
+
+    let x = lexer.next().unwrap().unwrap();
+    assert_eq!(x.r#type, TokenType::Identifier(Cow::from("x")));
+    assert_eq!(*x.position(), Position { row: 1, column: 30 });
+
+    let invalid = [
+        r#""\u{}""#,
+        r#""\u{12G4}""#,
+        r#""\u1234""#,
+        r#""\u{1234""#,
+        r#""\u{D800}""#,
+        r#""\u{110000}""#,
+        r#""\u{0000001}""#,
+    ];
+    for source in invalid {
+        let mut lexer = Lexer::new(source.chars());
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind,
+            LexingErrorKind::InvalidUnicodeEscape
+        );
+    }
+}
+
+#[test]
+pub fn test_byte_escapes() {
+    let mut lexer = Lexer::new(r#"'\x7F' "\x00\x01" '\xE9'"#.chars());
+    let expected = [
+        TokenType::Character {
+            value: '\x7F',
+            width: None,
+        },
+        TokenType::String(Cow::from("\x00\x01")),
+        TokenType::Character {
+            value: '\u{E9}',
+            width: None,
+        },
+    ];
+    for token in expected {
+        assert_eq!(lexer.next().unwrap().unwrap().r#type, token);
+    }
+
+    for source in [r#""\x7""#, r#""\xG0""#, r#""\x""#] {
+        let mut lexer = Lexer::new(source.chars());
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind,
+            LexingErrorKind::InvalidEscapeSequence
+        );
+    }
+}
+
+#[test]
+pub fn test_raw_strings() {
+    let mut lexer = Lexer::new(r#"r"C:\path\file" r"a\" b r r2"#.chars());
+    let expected = [
+        TokenType::String(Cow::from(r"C:\path\file")),
+        TokenType::String(Cow::from(r"a\")),
+        TokenType::Identifier(Cow::from("b")),
+        TokenType::Identifier(Cow::from("r")),
+        TokenType::Identifier(Cow::from("r2")),
+    ];
+    for token in expected {
+        assert_eq!(lexer.next().unwrap().unwrap().r#type, token);
+    }
+
+    let mut lexer = Lexer::new(r#"r"abc\"#.chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::IncompleteString
+    );
+}
+
+#[test]
+pub fn test_multi_line_strings() {
+    let source = "\"one\ntwo\" a\nvalue = \"\"\"\n    first\n      second\\n\n    \"\"\" x";
+    let mut lexer = Lexer::new(source.chars());
+
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::String(Cow::from("one\ntwo"))
+    );
+    let a = lexer.next().unwrap().unwrap();
+    assert_eq!(*a.position(), Position { row: 2, column: 6 });
+
+    lexer.next().unwrap().unwrap();
+    lexer.next().unwrap().unwrap();
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::String(Cow::from("first\n  second\n"))
+    );
+    let x = lexer.next().unwrap().unwrap();
+    assert_eq!(x.r#type, TokenType::Identifier(Cow::from("x")));
+    assert_eq!(*x.position(), Position { row: 6, column: 9 });
+
+    let mut lexer = Lexer::new("\"\"\"abc\"\"\" \"\" \"\"\"unterminated\"\"".chars());
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::String(Cow::from("abc"))
+    );
+    lexer.next().unwrap().unwrap();
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::IncompleteString
+    );
+}
+
+#[test]
+pub fn test_token_termination() {
+    let cases = [
+        (
+            "a+b",
+            vec![
+                (identifier("a"), 1),
+                (TokenType::PlusSign, 2),
+                (identifier("b"), 3),
+            ],
+        ),
+        (
+            "1+2",
+            vec![
+                (
+                    TokenType::Integer {
+                        value: 1,
+                        suffix: None,
+                    },
+                    1,
+                ),
+                (TokenType::PlusSign, 2),
+                (
+                    TokenType::Integer {
+                        value: 2,
+                        suffix: None,
+                    },
+                    3,
+                ),
+            ],
+        ),
+        (
+            "x:int",
+            vec![
+                (identifier("x"), 1),
+                (TokenType::Colon, 2),
+                (TokenType::Int, 3),
+            ],
+        ),
+        (
+            "foo(bar)",
+            vec![
+                (identifier("foo"), 1),
+                (TokenType::LeftParenthesis, 4),
+                (identifier("bar"), 5),
+                (TokenType::RightParenthesis, 8),
+            ],
+        ),
+    ];
+
+    for (source, mut expected) in cases {
+        expected.push((TokenType::EndOfFile, source.len() as u32 + 1));
+        let tokens: Vec<Token> = Lexer::new(source.chars()).map(Result::unwrap).collect();
+        assert_eq!(tokens.len(), expected.len(), "{source}");
+        for (token, (r#type, column)) in tokens.iter().zip(expected) {
+            assert_eq!(token.r#type, r#type);
+            assert_eq!(*token.position(), Position { row: 1, column });
+        }
+    }
+}
+
+#[test]
+pub fn test_lexemes() {
+    let source = "-021 21.210 'a' \"\\u{E9}\" bit8 #[ skipped ]# ->";
+    let lexemes: Vec<String> = Lexer::from_str(source)
+        .with_signed_literals()
+        .map(|token| token.unwrap().lexeme().to_string())
+        .collect();
+    assert_eq!(
+        lexemes,
+        ["-021", "21.210", "'a'", "\"\\u{E9}\"", "bit8", "->", ""]
+    );
+}
+
+#[test]
+pub fn test_error_positions() {
+    let cases = [
+        ("  \"abc", LexingErrorKind::IncompleteString, 1, 3, 7),
+        ("a\n 1.2.3", LexingErrorKind::MultipleDecimalPoints, 2, 2, 7),
+        ("x €", LexingErrorKind::UnknownToken, 1, 3, 4),
+        ("\n\n'ab'", LexingErrorKind::CharacterTooLong, 3, 1, 5),
+    ];
+
+    for (source, kind, row, column, end) in cases {
+        let error = Lexer::new(source.chars()).find_map(Result::err).unwrap();
+        assert_eq!(
+            error,
+            LexingError {
+                kind,
+                position: Position { row, column },
+                end: Position { row, column: end },
+            }
+        );
+    }
+}
+
+#[test]
+pub fn test_error_recovery() {
+    let mut lexer = Lexer::new("a ` b\n§c + €\n d 1__0abc+e".chars());
+    let LexOutput { tokens, errors } = lexer.lex_all();
+
+    let types: Vec<TokenType> = tokens.into_iter().map(|token| token.r#type).collect();
+    assert_eq!(
+        types,
+        [
+            identifier("a"),
+            identifier("b"),
+            identifier("c"),
+            TokenType::PlusSign,
+            identifier("d"),
+            TokenType::PlusSign,
+            identifier("e"),
+            TokenType::EndOfFile,
+        ]
+    );
+
+    let error = |kind, row, column, end| LexingError {
+        kind,
+        position: Position { row, column },
+        end: Position { row, column: end },
+    };
+    assert_eq!(
+        errors,
+        [
+            error(LexingErrorKind::UnknownToken, 1, 3, 4),
+            error(LexingErrorKind::UnknownToken, 2, 1, 2),
+            error(LexingErrorKind::UnknownToken, 2, 6, 7),
+            error(LexingErrorKind::MalformedNumericSeparator, 3, 4, 11),
+        ]
+    );
+}
+
+#[test]
+pub fn test_end_of_file() {
+    for source in ["", "  \n\t "] {
+        let mut lexer = Lexer::new(source.chars());
+        let end = lexer.next().unwrap().unwrap();
+        assert_eq!(end.r#type, TokenType::EndOfFile);
+        assert_eq!(end.lexeme(), "");
+        assert!(lexer.next().is_none());
+    }
+
+    let mut lexer = Lexer::new("a\n".chars());
+    lexer.next().unwrap().unwrap();
+    let end = lexer.next().unwrap().unwrap();
+    assert_eq!(end.r#type, TokenType::EndOfFile);
+    assert_eq!(*end.position(), Position { row: 2, column: 1 });
+}
+
+#[test]
+pub fn test_fused() {
+    fn assert_fused<I: FusedIterator>(_: &I) {}
+
+    for source in ["a", "\"abc", "'a", "'"] {
+        let mut lexer = Lexer::from_str(source);
+        assert_fused(&lexer);
+        let last = lexer.by_ref().last().unwrap().unwrap();
+        assert_eq!(last.r#type, TokenType::EndOfFile, "{source}");
+        for _ in 0..3 {
+            assert!(lexer.next().is_none(), "{source}");
+        }
+
+        let mut streaming = StreamingLexer::new(source.as_bytes());
+        assert_fused(&streaming);
+        streaming.by_ref().for_each(drop);
+        for _ in 0..3 {
+            assert!(streaming.next().is_none(), "{source}");
+        }
+    }
+
+    let mut lexer = Lexer::from_str("\"abc");
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::IncompleteString
+    );
+    let mut lexer = Lexer::from_str("'a");
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        LexingErrorKind::IncompleteCharacter
+    );
+}
+
+#[test]
+pub fn test_double_equals() {
+    assert_eq!(
+        lex_types("a==b"),
+        [identifier("a"), TokenType::DoubleEquals, identifier("b")]
+    );
+    assert_eq!(
+        lex_types("a = =b"),
+        [
+            identifier("a"),
+            TokenType::EqualsSign,
+            TokenType::EqualsSign,
+            identifier("b"),
+        ]
+    );
+
+    let mut lexer = Lexer::new("x ==".chars());
+    lexer.next().unwrap().unwrap();
+    let equals = lexer.next().unwrap().unwrap();
+    assert_eq!(equals.r#type, TokenType::DoubleEquals);
+    assert_eq!(equals.span().start, Position { row: 1, column: 3 });
+    assert_eq!(equals.span().end, Position { row: 1, column: 5 });
+}
+
+#[test]
+pub fn test_not_equals() {
+    assert_eq!(
+        lex_types("a != b"),
+        [identifier("a"), TokenType::NotEquals, identifier("b")]
+    );
+    assert_eq!(
+        lex_types("derive!(X)"),
+        [
+            TokenType::MacroIdentifier(Cow::from("derive")),
+            TokenType::LeftParenthesis,
+            identifier("X"),
+            TokenType::RightParenthesis,
+        ]
+    );
+    assert_eq!(lex_types("!"), [TokenType::ExclamationMark]);
+
+    let mut lexer = Lexer::new("!=".chars());
+    let span = lexer.next().unwrap().unwrap().span().clone();
+    assert_eq!(span.end, Position { row: 1, column: 3 });
+}
+
+#[test]
+pub fn test_relational_operators() {
+    assert_eq!(
+        lex_types("type MyType<TypeType> = int;"),
+        [
+            TokenType::Type,
+            identifier("MyType"),
+            TokenType::LeftAngleBracket,
+            identifier("TypeType"),
+            TokenType::RightAngleBracket,
+            TokenType::EqualsSign,
+            TokenType::Int,
+            TokenType::Semicolon,
+        ]
+    );
+    assert_eq!(
+        lex_types("a <= b >= c"),
+        [
+            identifier("a"),
+            TokenType::LessThanOrEqual,
+            identifier("b"),
+            TokenType::GreaterThanOrEqual,
+            identifier("c"),
+        ]
+    );
+    assert_eq!(
+        lex_types("<a>b"),
+        [
+            TokenType::LeftAngleBracket,
+            identifier("a"),
+            TokenType::RightAngleBracket,
+            identifier("b"),
+        ]
+    );
+}
+
+#[test]
+pub fn test_logical_operators() {
+    assert_eq!(
+        lex_types("a && b || c"),
+        [
+            identifier("a"),
+            TokenType::DoubleAmpersand,
+            identifier("b"),
+            TokenType::DoubleVerticalLine,
+            identifier("c"),
+        ]
+    );
+    assert_eq!(
+        lex_types("(Monday | Tuesday)"),
+        [
+            TokenType::LeftParenthesis,
+            identifier("Monday"),
+            TokenType::VerticalLine,
+            identifier("Tuesday"),
+            TokenType::RightParenthesis,
+        ]
+    );
+    assert_eq!(lex_types("&x"), [TokenType::Ampersand, identifier("x")]);
+
+    let mut lexer = Lexer::new(" && ".chars());
+    let span = lexer.next().unwrap().unwrap().span().clone();
+    assert_eq!(span.start, Position { row: 1, column: 2 });
+    assert_eq!(span.end, Position { row: 1, column: 4 });
+}
+
+#[test]
+pub fn test_double_colon() {
+    let mut lexer = Lexer::new("core::ObjectType".chars());
+    lexer.next().unwrap().unwrap();
+    let separator = lexer.next().unwrap().unwrap();
+    assert_eq!(separator.r#type, TokenType::DoubleColon);
+    assert_eq!(separator.span().end, Position { row: 1, column: 7 });
+    let object_type = lexer.next().unwrap().unwrap();
+    assert_eq!(object_type.r#type, identifier("ObjectType"));
+    assert_eq!(*object_type.position(), Position { row: 1, column: 7 });
+
+    assert_eq!(
+        lex_types("a: b"),
+        [identifier("a"), TokenType::Colon, identifier("b")]
+    );
+    assert_eq!(
+        lex_types("a:: b"),
+        [identifier("a"), TokenType::DoubleColon, identifier("b")]
+    );
+}
+
+#[test]
+pub fn test_compound_assignment() {
+    let x = || identifier("x");
+    assert_eq!(
+        lex_types("x += 1"),
+        [
+            x(),
+            TokenType::PlusEquals,
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(
+        lex_types("x +=1"),
+        [
+            x(),
+            TokenType::PlusEquals,
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(
+        lex_types("x + = 1"),
+        [
+            x(),
+            TokenType::PlusSign,
+            TokenType::EqualsSign,
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+        ]
+    );
+    assert_eq!(
+        lex_types("x -= -2"),
+        [
+            x(),
+            TokenType::MinusEquals,
+            TokenType::MinuxSign,
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(
+        lex_types("x *= 2 x /= 2"),
+        [
+            x(),
+            TokenType::AsteriskEquals,
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            },
+            x(),
+            TokenType::SolidusEquals,
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn test_shift_operators() {
+    assert_eq!(
+        lex_types("1 << 3"),
+        [
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::DoubleLeftAngle,
+            TokenType::Integer {
+                value: 3,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(
+        lex_types("x >> 2"),
+        [
+            identifier("x"),
+            TokenType::DoubleRightAngle,
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            }
+        ]
+    );
+
+    let LexOutput { tokens, errors } = lex_all("List<List<int>>");
+    assert!(errors.is_empty());
+    let types: Vec<&TokenType> = tokens.iter().map(Token::r#type).collect();
+    assert_eq!(
+        types,
+        [
+            &identifier("List"),
+            &TokenType::LeftAngleBracket,
+            &identifier("List"),
+            &TokenType::LeftAngleBracket,
+            &TokenType::Int,
+            &TokenType::DoubleRightAngle,
+            &TokenType::EndOfFile,
+        ]
+    );
+
+    let (first, second) = tokens[5].split_double_right_angle().unwrap();
+    assert_eq!(first.r#type, TokenType::RightAngleBracket);
+    assert_eq!(first.span().start, Position { row: 1, column: 14 });
+    assert_eq!(first.span().end, Position { row: 1, column: 15 });
+    assert_eq!(second.r#type, TokenType::RightAngleBracket);
+    assert_eq!(second.span().start, Position { row: 1, column: 15 });
+    assert_eq!(second.span().end, Position { row: 1, column: 16 });
+    assert_eq!(second.lexeme(), ">");
+    assert!(tokens[4].split_double_right_angle().is_none());
+}
+
+#[test]
+pub fn test_remainder_and_bitwise_operators() {
+    assert_eq!(
+        lex_types("a % b ^ ~c $ a %= 2 b ^= c"),
+        [
+            identifier("a"),
+            TokenType::PercentSign,
+            identifier("b"),
+            TokenType::CircumflexAccent,
+            TokenType::Tilde,
+            identifier("c"),
+            TokenType::DollarSign,
+            identifier("a"),
+            TokenType::PercentEquals,
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            },
+            identifier("b"),
+            TokenType::CircumflexEquals,
+            identifier("c"),
+        ]
+    );
+}
+
+#[test]
+pub fn test_fat_arrow() {
+    assert_eq!(
+        lex_types("x => 1"),
+        [
+            identifier("x"),
+            TokenType::RightwardsDoubleArrow,
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+        ]
+    );
+    assert_eq!(
+        lex_types("= >"),
+        [TokenType::EqualsSign, TokenType::RightAngleBracket]
+    );
+    // The longest operator at each point wins, so `==` is taken before `=>`.
+    assert_eq!(
+        lex_types("==>"),
+        [TokenType::DoubleEquals, TokenType::RightAngleBracket]
+    );
+}
+
+#[test]
+pub fn test_range_operators() {
+    assert_eq!(
+        lex_types("1..5"),
+        [
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::DoubleFullStop,
+            TokenType::Integer {
+                value: 5,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(
+        lex_types("1..=5"),
+        [
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::DoubleFullStopEquals,
+            TokenType::Integer {
+                value: 5,
+                suffix: None
+            },
+        ]
+    );
+    assert_eq!(
+        lex_types("1.5"),
+        [TokenType::Decimal {
+            value: 1.5,
+            suffix: None
+        }]
+    );
+    assert_eq!(
+        lex_types("a.b"),
+        [identifier("a"), TokenType::FullStop, identifier("b")]
+    );
+    // A trailing point is never part of the number.
+    assert_eq!(
+        lex_types("1."),
+        [
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::FullStop
+        ]
+    );
+}
+
+#[test]
+pub fn test_sized_types() {
+    assert_eq!(
+        lex_types("bit1 bit3 bit64 int24 float128 char7 bitfield bit_3"),
+        [
+            TokenType::BitN(1),
+            TokenType::BitN(3),
+            TokenType::Bit64,
+            TokenType::IntN(24),
+            TokenType::FloatN(128),
+            TokenType::CharN(7),
+            identifier("bitfield"),
+            identifier("bit_3"),
+        ]
+    );
+
+    for source in ["bit0", "bit99999", "int65536"] {
+        let mut lexer = Lexer::new(source.chars());
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind,
+            LexingErrorKind::InvalidTypeWidth
+        );
+    }
+}
+
+#[test]
+pub fn test_null_keyword() {
+    assert_eq!(
+        lex_types("null nullable"),
+        [TokenType::Null, identifier("nullable")]
+    );
+}
+
+#[test]
+pub fn test_keyword_spelling() {
+    assert_eq!(
+        lex_types("alias aliased"),
+        [TokenType::Alias, identifier("aliased")]
+    );
+
+    assert_eq!(TokenType::Alias.keyword_str(), Some("alias"));
+    assert_eq!(TokenType::Float64.keyword_str(), Some("float64"));
+    assert_eq!(TokenType::Boolean(false).keyword_str(), Some("false"));
+    assert_eq!(TokenType::Comma.keyword_str(), None);
+    assert_eq!(identifier("alias").keyword_str(), None);
+    for (spelling, keyword) in KEYWORDS.entries() {
+        assert_eq!(keyword.keyword_str(), Some(*spelling));
+    }
+}
+
+#[test]
+pub fn test_self_keywords() {
+    assert_eq!(
+        lex_types("self.field1 Self selfish Selfhood"),
+        [
+            TokenType::LowerSelf,
+            TokenType::FullStop,
+            identifier("field1"),
+            TokenType::UpperSelf,
+            identifier("selfish"),
+            identifier("Selfhood"),
+        ]
+    );
+}
+
+#[test]
+pub fn test_macro_identifiers() {
+    let mut lexer = Lexer::new("derive!(Debuggable)".chars());
+    let derive = lexer.next().unwrap().unwrap();
+    assert_eq!(
+        derive.r#type,
+        TokenType::MacroIdentifier(Cow::from("derive"))
+    );
+    assert_eq!(derive.lexeme(), "derive!");
+    assert_eq!(derive.span().end, Position { row: 1, column: 8 });
+
+    assert_eq!(
+        lex_types("offset_of!(bits)"),
+        [
+            TokenType::MacroIdentifier(Cow::from("offset_of")),
+            TokenType::LeftParenthesis,
+            identifier("bits"),
+            TokenType::RightParenthesis,
+        ]
+    );
+    assert_eq!(
+        lex_types("derive !"),
+        [identifier("derive"), TokenType::ExclamationMark]
+    );
+    assert_eq!(
+        lex_types("a!b"),
+        [TokenType::MacroIdentifier(Cow::from("a")), identifier("b")]
+    );
+    assert_eq!(
+        lex_types("a!=b"),
+        [identifier("a"), TokenType::NotEquals, identifier("b")]
+    );
+}
+
+#[test]
+pub fn test_tab_width() {
+    let source = "\ta\n\t\tb\n  \tc";
+    for (tab_width, columns) in [(1, [2, 3, 4]), (4, [5, 9, 5]), (8, [9, 17, 9])] {
+        let lexer = Lexer::new(source.chars()).with_tab_width(tab_width);
+        let positions: Vec<Position> = lexer
+            .take(3)
+            .map(|token| token.unwrap().position().clone())
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                Position {
+                    row: 1,
+                    column: columns[0]
+                },
+                Position {
+                    row: 2,
+                    column: columns[1]
+                },
+                Position {
+                    row: 3,
+                    column: columns[2]
+                },
+            ]
+        );
+    }
+}
+
+#[test]
+pub fn test_byte_order_mark() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/lexing_test_file.xi"
+    ))
+    .unwrap();
+    let with_bom = format!("\u{FEFF}{source}");
+
+    assert_eq!(
+        Lexer::from_str(&with_bom).collect::<Vec<_>>(),
+        Lexer::from_str(&source).collect::<Vec<_>>()
+    );
+
+    let error = Lexer::new("a \u{FEFF}".chars())
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!(error.kind, LexingErrorKind::UnexpectedBom);
+    assert_eq!(error.position, Position { row: 1, column: 3 });
+}
+
+#[test]
+pub fn test_token_start_positions() {
+    let source = "first\n    spaced  \"multi\nline\" after";
+    let positions: Vec<Position> = Lexer::new(source.chars())
+        .map(|token| token.unwrap().position().clone())
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            Position { row: 1, column: 1 },
+            Position { row: 2, column: 5 },
+            Position { row: 2, column: 13 },
+            Position { row: 3, column: 7 },
+            Position { row: 3, column: 12 },
+        ]
+    );
+}
+
+#[test]
+pub fn test_trace() {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &TraceEvent) {
+        let event = match event {
+            TraceEvent::Token(token) => format!(
+                "{:?} at {}:{}",
+                token.r#type,
+                token.position().row,
+                token.position().column
+            ),
+            TraceEvent::Error(error) => format!(
+                "{:?} at {}:{}",
+                error.kind, error.position.row, error.position.column
+            ),
+        };
+        EVENTS.with(|events| events.borrow_mut().push(event));
+    }
+
+    let mut lexer = Lexer::new("'a' €\nx".chars());
+    lexer.set_trace(record);
+    lexer.lex_all();
+
+    EVENTS.with(|events| {
+        assert_eq!(
+            *events.borrow(),
+            [
+                "Character { value: 'a', width: None } at 1:1",
+                "UnknownToken at 1:5",
+                "Identifier(\"x\") at 2:1",
+                "EndOfFile at 2:2",
+            ]
+        );
+    });
+}
+
+#[test]
+pub fn test_peek() {
+    let mut lexer = Lexer::from_str("a b\nc");
+    assert_eq!(
+        lexer.peek().unwrap().as_ref().unwrap().r#type,
+        identifier("a")
+    );
+    assert_eq!(*lexer.position(), Position { row: 1, column: 1 });
+
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("a"));
+    assert_eq!(*lexer.position(), Position { row: 1, column: 2 });
+
+    let c = lexer.peek_n(1).unwrap().as_ref().unwrap();
+    assert_eq!(c.r#type, identifier("c"));
+    assert_eq!(*c.position(), Position { row: 2, column: 1 });
+    assert_eq!(*lexer.position(), Position { row: 1, column: 2 });
+
+    assert_eq!(
+        lexer.peek().unwrap().as_ref().unwrap().r#type,
+        identifier("b")
+    );
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("b"));
+    assert_eq!(*lexer.position(), Position { row: 1, column: 4 });
+
+    assert_eq!(
+        lexer.peek_n(1).unwrap().as_ref().unwrap().r#type,
+        TokenType::EndOfFile
+    );
+    assert!(lexer.peek_n(2).is_none());
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("c"));
+    assert_eq!(*lexer.position(), Position { row: 2, column: 2 });
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, TokenType::EndOfFile);
+    assert!(lexer.peek().is_none());
+    assert!(lexer.next().is_none());
+}
+
+#[test]
+pub fn test_checkpoint() {
+    let mut lexer = Lexer::from_str("a -> b\n  c == 'd' e");
+    for _ in 0..3 {
+        lexer.next().unwrap().unwrap();
+    }
+
+    let checkpoint = lexer.checkpoint();
+    let first: Vec<Token> = (0..2).map(|_| lexer.next().unwrap().unwrap()).collect();
+    assert_eq!(first[0].r#type, identifier("c"));
+    assert_eq!(*first[0].position(), Position { row: 2, column: 3 });
+
+    lexer.rewind(checkpoint);
+    assert_eq!(*lexer.position(), Position { row: 1, column: 7 });
+    let second: Vec<Token> = (0..2).map(|_| lexer.next().unwrap().unwrap()).collect();
+    assert_eq!(first, second);
+
+    // Peeked tokens are part of the checkpoint too.
+    lexer.peek_n(1);
+    let checkpoint = lexer.checkpoint();
+    let first: Vec<Token> = lexer.by_ref().map(Result::unwrap).collect();
+    lexer.rewind(checkpoint);
+    let second: Vec<Token> = lexer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(first, second);
+    assert_eq!(
+        first
+            .iter()
+            .map(|token| token.r#type.clone())
+            .collect::<Vec<_>>(),
+        [
+            TokenType::Character {
+                value: 'd',
+                width: None
+            },
+            identifier("e"),
+            TokenType::EndOfFile
+        ]
+    );
+}
+
+#[test]
+pub fn test_zero_copy() {
+    let source = "module main\nfunction add(a: int32, b: bit7) -> int32 {\n    \
+                  return a + b * 21.5e3 - 0xFF >> 2 != derive!(x);\n}\n\
+                  value s = \"plain\" r\"C:\\raw\" 'c' \"\" Self;\n";
+
+    // Without escapes, every lexeme and piece of text is borrowed from the
+    // source.
+    let mut count = 0;
+    for token in Lexer::from_str(source) {
+        let token = token.unwrap();
+        assert!(matches!(token.lexeme, Cow::Borrowed(_)), "{token:?}");
+        if let TokenType::Identifier(text)
+        | TokenType::MacroIdentifier(text)
+        | TokenType::DocComment(text)
+        | TokenType::String(text) = &token.r#type
+        {
+            assert!(matches!(text, Cow::Borrowed(_)), "{token:?}");
+        }
+        count += 1;
+    }
+    assert_eq!(count, 43);
+
+    let types = lex_types(r#"plain "a\tb" "left\nright\"" r"raw\n""#);
+    assert!(matches!(
+        &types[0],
+        TokenType::Identifier(Cow::Borrowed("plain"))
+    ));
+    assert!(matches!(&types[1], TokenType::String(Cow::Owned(string)) if string == "a\tb"));
+    assert!(
+        matches!(&types[2], TokenType::String(Cow::Owned(string)) if string == "left\nright\"")
+    );
+    assert!(matches!(
+        &types[3],
+        TokenType::String(Cow::Borrowed(r"raw\n"))
+    ));
+}
+
+#[test]
+pub fn test_many_minus_signs() {
+    let source = format!("{}-1 -> -= -x", "- ".repeat(10_000));
+    let types = lex_types(&source);
+    assert_eq!(types.len(), 10_006);
+    assert!(types[..10_000]
+        .iter()
+        .all(|r#type| *r#type == TokenType::MinuxSign));
+    assert_eq!(
+        types[10_000..],
+        [
+            TokenType::MinuxSign,
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::RightwardsArrow,
+            TokenType::MinusEquals,
+            TokenType::MinuxSign,
+            identifier("x"),
+        ]
+    );
+}
+
+#[test]
+pub fn test_streaming_lexer() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/lexing_test_file.xi"
+    ))
+    .unwrap();
+    let expected: Vec<_> = Lexer::from_str(&source).collect();
+    for chunk_size in [1, 7, 8192] {
+        let streamed: Vec<_> = StreamingLexer::new(source.as_bytes())
+            .with_chunk_size(chunk_size)
+            .collect();
+        assert_eq!(streamed, expected);
+    }
+
+    // Multi-byte characters split across chunks, a byte order mark, and errors.
+    let source = "\u{FEFF}héllo \"€uro\" '😀' ` wörld!(x) #[ ünterminated";
+    let expected: Vec<_> = Lexer::from_str(source).collect();
+    for chunk_size in [1, 7] {
+        let streamed: Vec<_> = StreamingLexer::new(source.as_bytes())
+            .with_chunk_size(chunk_size)
+            .collect();
+        assert_eq!(streamed, expected);
+    }
+    assert_eq!(
+        expected[expected.len() - 2].as_ref().unwrap_err().kind,
+        LexingErrorKind::UnterminatedComment
+    );
+
+    // However the input is split, it lexes the same as all at once, even
+    // with a chunk boundary inside an interpolation.
+    let sources = [
+        r#""\(aaaa + bbbb + cccc + dddd)" x"#,
+        r#"a "x \(f("y \(z)") + 1) w" c"#,
+        r#""\(a ` b) c" d "\(e + "f\qg") h" i"#,
+        "\"\\(a +\n  b\" c",
+    ];
+    for source in sources {
+        let expected: Vec<_> = Lexer::from_str(source).collect();
+        for chunk_size in 1..=source.len() {
+            let streamed: Vec<_> = StreamingLexer::new(source.as_bytes())
+                .with_chunk_size(chunk_size)
+                .collect();
+            assert_eq!(streamed, expected, "{source} in chunks of {chunk_size}");
+        }
+    }
+}
+
+#[test]
+pub fn test_lex_all() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/lexing_test_file.xi"
+    ))
+    .unwrap();
+    let output = lex_all(&source);
+    assert_eq!(
+        output.errors,
+        [LexingError {
+            kind: LexingErrorKind::EmptyCharacter,
+            position: Position { row: 9, column: 1 },
+            end: Position { row: 9, column: 3 },
+        }]
+    );
+    assert_eq!(
+        output.tokens.len(),
+        Lexer::from_str(&source).filter(Result::is_ok).count()
+    );
+    assert_eq!(output.tokens.last().unwrap().r#type, TokenType::EndOfFile);
+
+    let output = lex_all("a ` b 1__0 c");
+    assert_eq!(
+        output.errors,
+        [
+            LexingError {
+                kind: LexingErrorKind::UnknownToken,
+                position: Position { row: 1, column: 3 },
+                end: Position { row: 1, column: 4 },
+            },
+            LexingError {
+                kind: LexingErrorKind::MalformedNumericSeparator,
+                position: Position { row: 1, column: 7 },
+                end: Position { row: 1, column: 11 },
+            },
+        ]
+    );
+    assert_eq!(
+        output
+            .tokens
+            .into_iter()
+            .map(|token| token.r#type)
+            .collect::<Vec<_>>(),
+        [
+            identifier("a"),
+            identifier("b"),
+            identifier("c"),
+            TokenType::EndOfFile
+        ]
+    );
+}
+
+#[test]
+pub fn test_signs() {
+    let minus = || TokenType::MinuxSign;
+    assert_eq!(
+        lex_types("a-1"),
+        [
+            identifier("a"),
+            minus(),
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            }
+        ]
+    );
+    assert_eq!(lex_types("a - 1"), lex_types("a-1"));
+    assert_eq!(
+        lex_types("(-1)"),
+        [
+            TokenType::LeftParenthesis,
+            minus(),
+            TokenType::Integer {
+                value: 1,
+                suffix: None
+            },
+            TokenType::RightParenthesis,
+        ]
+    );
+    assert_eq!(
+        lex_types("return -x;"),
+        [
+            TokenType::Return,
+            minus(),
+            identifier("x"),
+            TokenType::Semicolon,
+        ]
+    );
+    assert_eq!(
+        lex_types("+2.5 a->b"),
+        [
+            TokenType::PlusSign,
+            TokenType::Decimal {
+                value: 2.5,
+                suffix: None
+            },
+            identifier("a"),
+            TokenType::RightwardsArrow,
+            identifier("b"),
+        ]
+    );
+
+    let types: Vec<TokenType> = Lexer::from_str("a -1 +2 - 3")
+        .with_signed_literals()
+        .map(|token| token.unwrap().r#type)
+        .collect();
+    assert_eq!(
+        types,
+        [
+            identifier("a"),
+            TokenType::Integer {
+                value: -1,
+                suffix: None
+            },
+            TokenType::Integer {
+                value: 2,
+                suffix: None
+            },
+            minus(),
+            TokenType::Integer {
+                value: 3,
+                suffix: None
+            },
+            TokenType::EndOfFile,
+        ]
+    );
+}
+
+#[test]
+pub fn test_numeric_suffixes() {
+    let suffixed = |value, suffix| TokenType::Bits {
+        value,
+        suffix: Some(suffix),
+    };
+    assert_eq!(
+        lex_types("255bit8 0xFFbit8 0b101bit 0xbeef"),
+        [
+            suffixed(255, NumericSuffix::Bit(Some(8))),
+            suffixed(255, NumericSuffix::Bit(Some(8))),
+            suffixed(5, NumericSuffix::Bit(None)),
+            TokenType::Bits {
+                value: 0xBEEF,
+                suffix: None
+            },
+        ]
+    );
+    assert_eq!(
+        lex_types("21int8 7int"),
+        [
+            TokenType::Integer {
+                value: 21,
+                suffix: Some(NumericSuffix::Int(Some(8)))
+            },
+            TokenType::Integer {
+                value: 7,
+                suffix: Some(NumericSuffix::Int(None))
+            },
+        ]
+    );
+    assert_eq!(
+        lex_types("2.5float32 2float 1e3float64"),
+        [
+            TokenType::Decimal {
+                value: 2.5,
+                suffix: Some(NumericSuffix::Float(Some(32)))
+            },
+            TokenType::Decimal {
+                value: 2.0,
+                suffix: Some(NumericSuffix::Float(None))
+            },
+            TokenType::Decimal {
+                value: 1e3,
+                suffix: Some(NumericSuffix::Float(Some(64)))
+            },
+        ]
+    );
+
+    let cases = [
+        ("1.5int8", LexingErrorKind::MismatchedSuffix),
+        ("2.0bit", LexingErrorKind::MismatchedSuffix),
+        ("21i8", LexingErrorKind::UnknownSuffix(String::from("i8"))),
+        ("0x1Fx", LexingErrorKind::UnknownSuffix(String::from("x"))),
+        ("1int0", LexingErrorKind::InvalidTypeWidth),
+    ];
+    for (source, kind) in cases {
+        let mut lexer = Lexer::from_str(source);
+        assert_eq!(lexer.next().unwrap().unwrap_err().kind, kind, "{source}");
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::EndOfFile,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+pub fn test_literal_out_of_range() {
+    let out_of_range = |literal: &str, target| LexingErrorKind::LiteralOutOfRange {
+        literal: literal.to_string(),
+        target,
+    };
+
+    let mut lexer = Lexer::from_str("x 99999999999999999999999 y");
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("x"));
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err(),
+        LexingError {
+            kind: out_of_range("99999999999999999999999", "int64"),
+            position: Position { row: 1, column: 3 },
+            end: Position { row: 1, column: 26 },
+        }
+    );
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("y"));
+
+    let mut lexer =
+        Lexer::from_str("\n -9223372036854775809 -9223372036854775808").with_signed_literals();
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err(),
+        LexingError {
+            kind: out_of_range("-9223372036854775809", "int64"),
+            position: Position { row: 2, column: 2 },
+            end: Position { row: 2, column: 22 },
+        }
+    );
+    assert_eq!(
+        lexer.next().unwrap().unwrap().r#type,
+        TokenType::Integer {
+            value: i64::MIN,
+            suffix: None
+        }
+    );
+
+    let decimal = format!("1{}.5", "0".repeat(400));
+    let mut lexer = Lexer::from_str(&decimal);
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().kind,
+        out_of_range(&decimal, "float64")
+    );
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, TokenType::EndOfFile);
+
+    assert_eq!(
+        Lexer::from_str("18446744073709551615int")
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .kind,
+        out_of_range("18446744073709551615int", "int64")
+    );
+    // Without signed literals, the parser is left to range-check the one
+    // magnitude that fits negated.
+    assert_eq!(
+        Lexer::from_str("9223372036854775808")
+            .next()
+            .unwrap()
+            .unwrap()
+            .r#type,
+        TokenType::Integer {
+            value: i64::MIN,
+            suffix: None
+        }
+    );
+    assert_eq!(
+        Lexer::from_str("9223372036854775809")
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .kind,
+        out_of_range("9223372036854775809", "int64")
+    );
+    assert_eq!(
+        Lexer::from_str("18446744073709551616bit")
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .kind,
+        out_of_range("18446744073709551616bit", "bit64")
+    );
+}
+
+#[test]
+pub fn test_underscore_identifiers() {
+    assert_eq!(
+        lex_types("_private __double _1 a_ _"),
+        [
+            identifier("_private"),
+            identifier("__double"),
+            identifier("_1"),
+            identifier("a_"),
+            TokenType::Underscore,
+        ]
+    );
+    assert_eq!(
+        lex_types("(_, x)"),
+        [
+            TokenType::LeftParenthesis,
+            TokenType::Underscore,
+            TokenType::Comma,
+            identifier("x"),
+            TokenType::RightParenthesis,
+        ]
+    );
+}
+
+#[test]
+pub fn test_bits_and_integer_kinds() {
+    assert_eq!(
+        lex_types("21 0xFF 21bit 18446744073709551615bit"),
+        [
+            TokenType::Integer {
+                value: 21,
+                suffix: None
+            },
+            TokenType::Bits {
+                value: 0xFF,
+                suffix: None
+            },
+            TokenType::Bits {
+                value: 21,
+                suffix: Some(NumericSuffix::Bit(None))
+            },
+            TokenType::Bits {
+                value: u64::MAX,
+                suffix: Some(NumericSuffix::Bit(None))
+            },
+        ]
+    );
+
+    // A sign only changes the value, not the kind.
+    let mut lexer = Lexer::from_str("+21 -21").with_signed_literals();
+    for value in [21, -21] {
+        assert_eq!(
+            lexer.next().unwrap().unwrap().r#type,
+            TokenType::Integer {
+                value,
+                suffix: None
+            }
+        );
+    }
+
+    // `b` alone isn't a suffix, since it would be ambiguous with the digits
+    // of a hexadecimal literal.
+    assert_eq!(
+        Lexer::from_str("21b").next().unwrap().unwrap_err().kind,
+        LexingErrorKind::UnknownSuffix(String::from("b"))
+    );
+}
+
+#[test]
+pub fn test_character_widths() {
+    let character = |value, width| TokenType::Character {
+        value,
+        width: Some(width),
+    };
+    assert_eq!(
+        lex_types(r"c8'a' c8'\u{E9}' c16'\u{20AC}' c32'\u{1F600}' c8 c16"),
+        [
+            character('a', CharWidth::Char8),
+            character('\u{E9}', CharWidth::Char8),
+            character('\u{20AC}', CharWidth::Char16),
+            character('\u{1F600}', CharWidth::Char32),
+            identifier("c8"),
+            identifier("c16"),
+        ]
+    );
+
+    let cases = [(r"c8'\u{20AC}'", "char8"), (r"c16'\u{1F600}'", "char16")];
+    for (source, target) in cases {
+        let mut lexer = Lexer::from_str(source);
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err(),
+            LexingError {
+                kind: LexingErrorKind::LiteralOutOfRange {
+                    literal: source.to_string(),
+                    target,
+                },
+                position: Position { row: 1, column: 1 },
+                end: Position {
+                    row: 1,
+                    column: source.len() as u32 + 1,
+                },
+            }
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().r#type, TokenType::EndOfFile);
+    }
+
+    // Nothing past U+10FFFF is a character at all.
+    assert_eq!(
+        Lexer::from_str(r"c32'\u{110000}'")
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .kind,
+        LexingErrorKind::InvalidUnicodeEscape
+    );
+}
+
+#[test]
+pub fn test_character_literal_length() {
+    assert_eq!(
+        lex_types(r"'\0' 'a'"),
+        [
+            TokenType::Character {
+                value: '\0',
+                width: None
+            },
+            TokenType::Character {
+                value: 'a',
+                width: None
+            },
+        ]
+    );
+
+    let cases = [
+        ("''", LexingErrorKind::EmptyCharacter),
+        ("'ab'", LexingErrorKind::CharacterTooLong),
+        (r"'a\n'", LexingErrorKind::CharacterTooLong),
+        ("'a", LexingErrorKind::IncompleteCharacter),
+        ("'ab\n'", LexingErrorKind::IncompleteCharacter),
+    ];
+    for (source, kind) in cases {
+        let mut lexer = Lexer::from_str(source);
+        assert_eq!(lexer.next().unwrap().unwrap_err().kind, kind, "{source}");
+    }
+
+    // Lexing picks up again after the offending literal.
+    let mut lexer = Lexer::from_str("'ab' x");
+    lexer.next().unwrap().unwrap_err();
+    assert_eq!(lexer.next().unwrap().unwrap().r#type, identifier("x"));
+}
+
+#[test]
+pub fn test_string_lengths() {
+    let lengths: Vec<usize> = lex_types(r#""" "\0" "a" "é""#)
+        .into_iter()
+        .map(|r#type| match r#type {
+            TokenType::String(string) => string.chars().count(),
+            _ => panic!("expected a string"),
+        })
+        .collect();
+    assert_eq!(lengths, [0, 1, 1, 1]);
+    assert_eq!(lex_types(r#""""#), [TokenType::String(Cow::from(""))]);
+}
+
+#[test]
+pub fn test_doc_comments() {
+    let doc = |text| TokenType::DocComment(Cow::Borrowed(text));
+    let source = "## A point on a plane.\n##\n##   Both coordinates are signed.\r\ntype Point;";
+    assert_eq!(
+        lex_types(source),
+        [
+            doc("A point on a plane."),
+            doc(""),
+            doc("Both coordinates are signed."),
+            TokenType::Type,
+            identifier("Point"),
+            TokenType::Semicolon,
+        ]
+    );
+
+    let mut lexer = Lexer::from_str("value x = 1; ## The answer.\ny");
+    let tokens: Vec<Token> = lexer.by_ref().map(Result::unwrap).collect();
+    assert_eq!(tokens[5].r#type, doc("The answer."));
+    assert_eq!(*tokens[5].position(), Position { row: 1, column: 14 });
+    assert_eq!(tokens[5].span().end, Position { row: 1, column: 28 });
+    assert_eq!(tokens[6].r#type, identifier("y"));
+
+    assert_eq!(lex_types("# #[ x ]# ##"), [TokenType::NumberSign, doc("")]);
+}
+
+#[test]
+pub fn test_as_keyword() {
+    assert_eq!(
+        lex_types("use core::ObjectType as Obj; x as int32 asset has"),
+        [
+            TokenType::Use,
+            identifier("core"),
+            TokenType::DoubleColon,
+            identifier("ObjectType"),
+            TokenType::As,
+            identifier("Obj"),
+            TokenType::Semicolon,
+            identifier("x"),
+            TokenType::As,
+            TokenType::Int32,
+            identifier("asset"),
+            identifier("has"),
+        ]
+    );
+    assert_eq!(TokenType::As.keyword_str(), Some("as"));
+}
+
+#[test]
+pub fn test_control_flow_keywords() {
+    assert_eq!(
+        lex_types("if else match while for in loop break continue"),
+        [
+            TokenType::If,
+            TokenType::Else,
+            TokenType::Match,
+            TokenType::While,
+            TokenType::For,
+            TokenType::In,
+            TokenType::Loop,
+            TokenType::Break,
+            TokenType::Continue,
+        ]
+    );
+    assert_eq!(
+        lex_types("iffy formatter inner elsewhere matches loops breaker"),
+        [
+            "iffy",
+            "formatter",
+            "inner",
+            "elsewhere",
+            "matches",
+            "loops",
+            "breaker"
+        ]
+        .map(identifier)
+    );
+}
+
+#[test]
+pub fn test_visibility_keywords() {
+    let types = lex_types("public function f private value x publication");
+    assert_eq!(
+        types,
+        [
+            TokenType::Public,
+            TokenType::Function,
+            identifier("f"),
+            TokenType::Private,
+            TokenType::Value,
+            identifier("x"),
+            identifier("publication"),
+        ]
+    );
+    let modifiers: Vec<bool> = types.iter().map(TokenType::is_item_modifier).collect();
+    assert_eq!(modifiers, [true, false, false, true, false, false, false]);
+}
+
+#[test]
+pub fn test_mutability_keywords() {
+    let types = lex_types("value mutable counter constant PI mutableish constants");
+    assert_eq!(
+        types,
+        [
+            TokenType::Value,
+            TokenType::Mutable,
+            identifier("counter"),
+            TokenType::Constant,
+            identifier("PI"),
+            identifier("mutableish"),
+            identifier("constants"),
+        ]
+    );
+    let mutability: Vec<bool> = types.iter().map(TokenType::is_mutability).collect();
+    assert_eq!(mutability, [false, true, false, true, false, false, false]);
+    assert_eq!(TokenType::Constant.keyword_str(), Some("constant"));
+}
+
+#[test]
+pub fn test_string_interpolation() {
+    let literal = |text| StringPart::Literal(Cow::Borrowed(text));
+    fn parts<'a>(r#type: &TokenType<'a>) -> Vec<Vec<TokenType<'a>>> {
+        let TokenType::InterpolatedString(parts) = r#type else {
+            panic!("expected an interpolated string");
+        };
+        parts
+            .iter()
+            .map(|part| match part {
+                StringPart::Literal(_) => Vec::new(),
+                StringPart::Tokens(tokens) => {
+                    tokens.iter().map(|token| token.r#type.clone()).collect()
+                }
+            })
+            .collect()
+    }
+
+    let source = r#""Hello, \(name)! You are \(f(x, y))\t""#;
+    let tokens: Vec<Token> = Lexer::from_str(source).map(Result::unwrap).collect();
+    let TokenType::InterpolatedString(string) = &tokens[0].r#type else {
+        panic!("expected an interpolated string");
+    };
+    assert_eq!(string[0], literal("Hello, "));
+    assert_eq!(string[2], literal("! You are "));
+    assert_eq!(
+        string[4],
+        StringPart::Literal(Cow::Owned(String::from("\t")))
+    );
+    assert_eq!(
+        parts(&tokens[0].r#type),
+        [
+            vec![],
+            vec![identifier("name")],
+            vec![],
+            vec![
+                identifier("f"),
+                TokenType::LeftParenthesis,
+                identifier("x"),
+                TokenType::Comma,
+                identifier("y"),
+                TokenType::RightParenthesis,
+            ],
+            vec![],
+        ]
+    );
+    let StringPart::Tokens(name) = &string[1] else {
+        panic!("expected tokens");
+    };
+    assert_eq!(*name[0].position(), Position { row: 1, column: 11 });
+    assert_eq!(tokens[0].span().end, Position { row: 1, column: 39 });
+    assert_eq!(tokens[1].r#type, TokenType::EndOfFile);
+
+    // Interpolations nest, and a string without any stays a plain string.
+    let nested = lex_types(r#""a\("b\(c)")" "plain""#);
+    assert_eq!(nested[1], TokenType::String(Cow::Borrowed("plain")));
+    let inner = parts(&nested[0]);
+    assert_eq!(inner.len(), 2);
+    assert!(matches!(inner[1][0], TokenType::InterpolatedString(_)));
+
+    assert_eq!(
+        Lexer::from_str(r#""a \(x + (y)"#)
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .kind,
+        LexingErrorKind::UnterminatedInterpolation
+    );
+
+    let deep = format!("{}x{}", r#""\("#.repeat(17), r#")""#.repeat(17));
+    assert_eq!(
+        Lexer::from_str(&deep).next().unwrap().unwrap_err().kind,
+        LexingErrorKind::InterpolationTooDeep
+    );
+    let shallow = format!("{}x{}", r#""\("#.repeat(16), r#")""#.repeat(16));
+    assert!(Lexer::from_str(&shallow).next().unwrap().is_ok());
+
+    // A bad token in the code is reported where it is, and the rest of the
+    // string is skipped.
+    let LexOutput { tokens, errors } = lex_all("\"a \\(b +\n €) c\" d");
+    assert_eq!(
+        errors,
+        [LexingError {
+            kind: LexingErrorKind::UnknownToken,
+            position: Position { row: 2, column: 2 },
+            end: Position { row: 2, column: 3 },
+        }]
+    );
+    let types: Vec<_> = tokens.into_iter().map(|token| token.r#type).collect();
+    assert_eq!(types, [identifier("d"), TokenType::EndOfFile]);
+}
+
+#[test]
+pub fn test_shebang() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/lexing_test_file.xi"
+    ))
+    .unwrap();
+    let script = format!("#!/usr/bin/env xic run\n{source}");
+
+    // The file's one error, the empty character, is left out on both sides.
+    let shifted: Vec<Token> = Lexer::from_str(&source)
+        .filter_map(Result::ok)
+        .map(|mut token| {
+            token.span.start.row += 1;
+            token.span.end.row += 1;
+            token
+        })
+        .collect();
+    let tokens: Vec<Token> = Lexer::from_str(&script).filter_map(Result::ok).collect();
+    assert_eq!(tokens, shifted);
+    assert_eq!(*tokens[0].position(), Position { row: 2, column: 1 });
+
+    let streamed: Vec<Token> = StreamingLexer::new(script.as_bytes())
+        .with_chunk_size(3)
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(streamed, shifted);
+
+    assert_eq!(
+        lex_types("x #!y"),
+        [
+            identifier("x"),
+            TokenType::NumberSign,
+            TokenType::ExclamationMark,
+            identifier("y"),
+        ]
+    );
+    assert_eq!(lex_types("#!"), []);
+}
+
+#[test]
+pub fn test_arbitrary_input() {
+    // Fragments that reach the lexer's less common paths, glued together at
+    // random along with arbitrary characters.
+    let fragments = [
+        "0x",
+        "0b",
+        "0o",
+        "1e",
+        "1.",
+        "..=",
+        "_",
+        "'",
+        "\"",
+        "\"\"\"",
+        "r\"",
+        "\\u{",
+        "\\x",
+        "\\(",
+        ")",
+        "#[",
+        "]#",
+        "##",
+        "#!",
+        "c8'",
+        "bit",
+        "int8",
+        "float0",
+        "\n",
+        "\t",
+        "\r",
+        "-",
+        "+",
+        "!",
+        "=",
+        ">>",
+        "\u{FEFF}",
+        "é",
+        "😀",
+        "9",
+        "99999999999999999999",
+    ];
+
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..10_000 {
+        let mut source = String::new();
+        for _ in 0..next() % 24 {
+            let choice = next();
+            if choice % 3 == 0 {
+                source.extend(char::from_u32((choice >> 8) as u32 % 0x11_0000));
+            } else {
+                source.push_str(fragments[(choice >> 8) as usize % fragments.len()]);
+            }
+        }
+
+        let output = lex_all(&source);
+        let results = output.tokens.len() + output.errors.len();
+        assert!(results <= source.chars().count() + 1, "{source:?}");
+        assert_eq!(
+            output.tokens.last().map(|token| &token.r#type),
+            Some(&TokenType::EndOfFile),
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+pub fn test_module_declaration() {
+    let ident = |name: &str, start, end| ast::Ident {
+        name: name.to_string(),
+        span: span((1, start), (1, end)),
+    };
+
+    let mut parser = Parser::new(Lexer::from_str("module MyModule;"));
+    assert_eq!(
+        parser.parse_module(),
+        Some(ast::Module {
+            name: ast::Path {
+                segments: vec![ident("MyModule", 8, 16)],
+                span: span((1, 8), (1, 16)),
+            },
+            items: Vec::new(),
+            span: span((1, 1), (1, 17)),
+        })
+    );
+    assert!(parser.errors().is_empty());
+
+    let mut parser = Parser::new(Lexer::from_str("module my::nested::Mod;"));
+    let module = parser.parse_module().unwrap();
+    assert_eq!(
+        module.name.segments,
+        [
+            ident("my", 8, 10),
+            ident("nested", 12, 18),
+            ident("Mod", 20, 23)
+        ]
+    );
+    assert_eq!(module.name.span, span((1, 8), (1, 23)));
+    assert!(parser.errors().is_empty());
+}
+
+#[test]
+pub fn test_module_declaration_errors() {
+    // A missing `;` is reported at whatever comes instead, but the module is
+    // still produced.
+    let mut parser = Parser::new(Lexer::from_str("module MyModule\nuse a;"));
+    let module = parser.parse_module().unwrap();
+    assert_eq!(module.name.segments[0].name, "MyModule");
+    assert_eq!(module.items.len(), 1);
+    assert_eq!(
+        parser.errors(),
+        [ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                TokenDescription::Token(TokenType::Use),
+                "in module declaration"
+            ),
+            span: span((2, 1), (2, 4)),
+        }]
+    );
+
+    let mut parser = Parser::new(Lexer::from_str("module type;"));
+    assert_eq!(parser.parse_module(), None);
+    assert_eq!(
+        parser.errors(),
+        [ParseError {
+            kind: ParseErrorKind::KeywordAsIdentifier("type"),
+            span: span((1, 8), (1, 12)),
+        }]
+    );
+
+    let mut parser = Parser::new(Lexer::from_str("module ;"));
+    assert_eq!(parser.parse_module(), None);
+    assert_eq!(
+        parser.errors(),
+        [ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 8), (1, 9)),
+        }]
+    );
+
+    let mut parser = Parser::new(Lexer::from_str("module my::;"));
+    assert_eq!(parser.parse_module(), None);
+    assert_eq!(parser.errors()[0].kind, ParseErrorKind::ExpectedIdentifier);
+
+    let mut parser = Parser::new(Lexer::from_str(""));
+    assert_eq!(parser.parse_module(), None);
+    assert_eq!(
+        parser.errors()[0].kind,
+        expected_token(
+            TokenType::Module,
+            TokenDescription::EndOfFile,
+            "at the start of the file"
+        )
+    );
+}
+
+#[test]
+pub fn test_parse_example_program() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/example_program.xi"
+    ))
+    .unwrap();
+
+    let mut parser = Parser::new(Lexer::from_str(&source));
+    let module = parser.parse_module().unwrap();
+    assert_eq!(module.name.segments[0].name, "MyModule");
+    assert!(parser.lexing_errors().is_empty());
+
+    // The example writes notes after `#`, which only starts a comment as
+    // `#[ ... ]#` or `##`. So `MyType` ends at the first one, with its `;`
+    // missing, and the rest of it, the stray `,` after `@bit8` included, is
+    // skipped as one error item. Every other item is there in the order it
+    // was written.
+    let items: Vec<_> = module
+        .items
+        .iter()
+        .map(|item| match &item.kind {
+            ast::ItemKind::Trait(decl) => format!("trait {}", decl.name.name),
+            ast::ItemKind::Type(decl) => format!("type {}", decl.name.name),
+            ast::ItemKind::Use(_) => String::from("use"),
+            ast::ItemKind::Extend(_) => String::from("extend"),
+            kind => format!("{kind:?}"),
+        })
+        .collect();
+    assert_eq!(
+        items,
+        [
+            "trait MyTrait",
+            "type BitField",
+            "type MyType",
+            "Error",
+            "use",
+            "extend"
+        ]
+    );
+    let rows: Vec<_> = module
+        .items
+        .iter()
+        .map(|item| item.span.start.row)
+        .collect();
+    assert_eq!(rows, [3, 10, 16, 16, 25, 27]);
+    let ast::ItemKind::Type(my_type) = &module.items[2].kind else {
+        unreachable!();
+    };
+    assert_eq!(my_type.fields.len(), 1);
+    assert_eq!(module.items[5].attributes[0].name.name, "derive");
+    // The skipped tokens aren't reported again. Neither is the `= ...` of
+    // `return Self = self.copy();`, where a return can only be followed by
+    // its value.
+    assert_eq!(
+        parser.errors(),
+        [
+            ParseError {
+                kind: expected_token(
+                    TokenType::Semicolon,
+                    TokenDescription::Token(TokenType::NumberSign),
+                    "in type declaration"
+                ),
+                span: span((16, 73), (16, 74)),
+            },
+            ParseError {
+                kind: expected_token(
+                    TokenType::Semicolon,
+                    TokenDescription::Token(TokenType::EqualsSign),
+                    "in return statement"
+                ),
+                span: span((39, 21), (39, 22)),
+            },
+        ]
+    );
+}
+
+#[test]
+pub fn test_use_declarations() {
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|segment| segment.to_string()).collect()
+    }
+    fn names(path: &ast::Path) -> Vec<String> {
+        path.segments
+            .iter()
+            .map(|segment| segment.name.clone())
+            .collect()
+    }
+    fn parse(source: &str) -> (Vec<ast::UseDecl>, Vec<ParseError>) {
+        let mut parser = Parser::new(Lexer::from_str(source));
+        let module = parser.parse_module().unwrap();
+        let uses = module
+            .items
+            .into_iter()
+            .map(|item| match item.kind {
+                ast::ItemKind::Use(decl) => decl,
+                kind => panic!("{kind:?}"),
+            })
+            .collect();
+        (uses, parser.errors().to_vec())
+    }
+
+    let (uses, errors) = parse("module m;\nuse core::ObjectType;\nuse a::b::C as D;");
+    assert!(errors.is_empty());
+    assert_eq!(uses.len(), 2);
+    assert_eq!(names(&uses[0].path), path(&["core", "ObjectType"]));
+    assert_eq!(uses[0].path.span, span((2, 5), (2, 21)));
+    assert_eq!(uses[0].kind, ast::UseKind::Single { alias: None });
+    assert_eq!(names(&uses[1].path), path(&["a", "b", "C"]));
+    assert_eq!(
+        uses[1].kind,
+        ast::UseKind::Single {
+            alias: Some(ast::Ident {
+                name: String::from("D"),
+                span: span((3, 16), (3, 17)),
+            })
+        }
+    );
+
+    let (uses, errors) = parse("module m; use core::{ObjectType, io::{Read as R, Write},};");
+    assert!(errors.is_empty());
+    assert_eq!(names(&uses[0].path), path(&["core"]));
+    let ast::UseKind::Group(group) = &uses[0].kind else {
+        panic!("{:?}", uses[0].kind);
+    };
+    assert_eq!(group.len(), 2);
+    assert_eq!(names(&group[0].path), path(&["ObjectType"]));
+    assert_eq!(names(&group[1].path), path(&["io"]));
+    let ast::UseKind::Group(nested) = &group[1].kind else {
+        panic!("{:?}", group[1].kind);
+    };
+    assert_eq!(names(&nested[0].path), path(&["Read"]));
+    assert!(matches!(
+        &nested[0].kind,
+        ast::UseKind::Single { alias: Some(alias) } if alias.name == "R"
+    ));
+    assert_eq!(names(&nested[1].path), path(&["Write"]));
+}
+
+#[test]
+pub fn test_use_declaration_errors() {
+    let errors = |source| {
+        let mut parser = Parser::new(Lexer::from_str(source));
+        parser.parse_module();
+        parser.errors().to_vec()
+    };
+
+    assert_eq!(
+        errors("module m; use core::;"),
+        [ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 21), (1, 22)),
+        }]
+    );
+    assert_eq!(
+        errors("module m; use core::ObjectType\nuse b;"),
+        [ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                TokenDescription::Token(TokenType::Use),
+                "in use declaration"
+            ),
+            span: span((2, 1), (2, 4)),
+        }]
+    );
+    assert_eq!(
+        errors("module m; use core::{ };"),
+        [ParseError {
+            kind: ParseErrorKind::EmptyUseGroup,
+            span: span((1, 21), (1, 24)),
+        }]
+    );
+    assert_eq!(
+        errors("module m; use core::{A B};"),
+        [ParseError {
+            kind: ParseErrorKind::Expected {
+                expected: vec![
+                    TokenDescription::Token(TokenType::Comma),
+                    TokenDescription::Token(TokenType::RightCurlyBracket),
+                ],
+                found: described("identifier", "B"),
+                context: "in use declaration",
+            },
+            span: span((1, 24), (1, 25)),
+        }]
+    );
+}
+
+#[cfg(test)]
+fn parse_type(source: &str) -> Result<ast::TypeExpr, ParseError> {
+    Parser::new(Lexer::from_str(source)).parse_type()
+}
+
+#[test]
+pub fn test_enumeration_types() {
+    let r#type = parse_type("(Monday | Tuesday | Thursday)").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 30)));
+    let ast::TypeExprKind::Enumeration(alternatives) = r#type.kind else {
+        panic!("{:?}", r#type.kind);
+    };
+    assert_eq!(
+        alternatives,
+        [
+            ast::Ident {
+                name: String::from("Monday"),
+                span: span((1, 2), (1, 8)),
+            },
+            ast::Ident {
+                name: String::from("Tuesday"),
+                span: span((1, 11), (1, 18)),
+            },
+            ast::Ident {
+                name: String::from("Thursday"),
+                span: span((1, 21), (1, 29)),
+            },
+        ]
+    );
+
+    // A single name in parentheses is only grouped, not an enumeration.
+    let r#type = parse_type("(Monday)").unwrap();
+    assert!(matches!(
+        r#type.kind,
+        ast::TypeExprKind::Named { ref path, .. } if path.segments[0].name == "Monday"
+    ));
+    assert_eq!(r#type.span, span((1, 1), (1, 9)));
+
+    assert_eq!(
+        parse_type("(Monday | Tuesday |)"),
+        Err(ParseError {
+            kind: ParseErrorKind::TrailingSeparator,
+            span: span((1, 19), (1, 20)),
+        })
+    );
+    assert_eq!(
+        parse_type("(a::B | C)"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 2), (1, 6)),
+        })
+    );
+    assert_eq!(
+        parse_type("(A | int)"),
+        Err(ParseError {
+            kind: ParseErrorKind::KeywordAsIdentifier("int"),
+            span: span((1, 6), (1, 9)),
+        })
+    );
+}
+
+#[test]
+pub fn test_array_types() {
+    let integer = |value, start, end| ast::Expr {
+        kind: ast::ExprKind::Literal(ast::Literal::Integer {
+            value,
+            suffix: None,
+        }),
+        span: span((1, start), (1, end)),
+    };
+
+    let r#type = parse_type("(int * 32)").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 11)));
+    let ast::TypeExprKind::Array { element, length } = r#type.kind else {
+        panic!("{:?}", r#type.kind);
+    };
+    assert!(matches!(
+        element.kind,
+        ast::TypeExprKind::Named { ref path, .. } if path.segments[0].name == "int"
+    ));
+    assert_eq!(*length, integer(32, 8, 10));
+
+    // Any `*` after the first multiplies within the length.
+    let r#type = parse_type("(bit * (32 - 4) * 2)").unwrap();
+    let ast::TypeExprKind::Array { length, .. } = r#type.kind else {
+        panic!("{:?}", r#type.kind);
+    };
+    assert_eq!(
+        *length,
+        ast::Expr {
+            kind: ast::ExprKind::Binary {
+                op: ast::BinaryOp::Multiply,
+                left: Box::new(ast::Expr {
+                    kind: ast::ExprKind::Binary {
+                        op: ast::BinaryOp::Subtract,
+                        left: Box::new(integer(32, 9, 11)),
+                        right: Box::new(integer(4, 14, 15)),
+                    },
+                    span: span((1, 8), (1, 16)),
+                }),
+                right: Box::new(integer(2, 19, 20)),
+            },
+            span: span((1, 8), (1, 20)),
+        }
+    );
+
+    assert_eq!(
+        parse_type("(int * )"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedExpression,
+            span: span((1, 8), (1, 9)),
+        })
+    );
+}
+
+#[test]
+pub fn test_union_types() {
+    fn named(r#type: &ast::TypeExpr) -> &str {
+        match &r#type.kind {
+            ast::TypeExprKind::Named { path, .. } => &path.segments[0].name,
+            kind => panic!("{kind:?}"),
+        }
+    }
+    fn members(r#type: ast::TypeExpr) -> Vec<ast::TypeExpr> {
+        match r#type.kind {
+            ast::TypeExprKind::Union(members) => members,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let union = members(parse_type("(int32 + int64)").unwrap());
+    assert_eq!(
+        union.iter().map(named).collect::<Vec<_>>(),
+        ["int32", "int64"]
+    );
+    assert_eq!(union[1].span, span((1, 10), (1, 15)));
+
+    let union = members(parse_type("(int8 + Number + bit64)").unwrap());
+    assert_eq!(
+        union.iter().map(named).collect::<Vec<_>>(),
+        ["int8", "Number", "bit64"]
+    );
+
+    let union = members(parse_type("((int, int) + bit64)").unwrap());
+    assert_eq!(union.len(), 2);
+    assert!(matches!(&union[0].kind, ast::TypeExprKind::Tuple(elements) if elements.len() == 2));
+    assert_eq!(union[0].span, span((1, 2), (1, 12)));
+    assert_eq!(named(&union[1]), "bit64");
+
+    assert_eq!(
+        parse_type("(int32 + int64 | bit8)"),
+        Err(ParseError {
+            kind: ParseErrorKind::AmbiguousComposite,
+            span: span((1, 16), (1, 17)),
+        })
+    );
+    assert_eq!(
+        parse_type("(A | B + C)"),
+        Err(ParseError {
+            kind: ParseErrorKind::AmbiguousComposite,
+            span: span((1, 8), (1, 9)),
+        })
+    );
+    assert_eq!(
+        parse_type("(int +)"),
+        Err(ParseError {
+            kind: ParseErrorKind::TrailingSeparator,
+            span: span((1, 6), (1, 7)),
+        })
+    );
+}
+
+#[test]
+pub fn test_pointer_types() {
+    fn pointer(r#type: ast::TypeExpr) -> (bool, ast::TypeExpr) {
+        match r#type.kind {
+            ast::TypeExprKind::Pointer { nullable, pointee } => (nullable, *pointee),
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let r#type = parse_type("@bit8").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 6)));
+    let (nullable, pointee) = pointer(r#type);
+    assert!(!nullable);
+    assert_eq!(pointee.span, span((1, 2), (1, 6)));
+
+    let (nullable, pointee) = pointer(parse_type("@?bit8").unwrap());
+    assert!(nullable);
+    assert!(matches!(pointee.kind, ast::TypeExprKind::Named { .. }));
+
+    let (nullable, pointee) = pointer(parse_type("@(int, int)").unwrap());
+    assert!(!nullable);
+    assert!(matches!(pointee.kind, ast::TypeExprKind::Tuple(ref elements) if elements.len() == 2));
+
+    let r#type = parse_type("@@?bit8").unwrap();
+    let (nullable, pointee) = pointer(r#type);
+    assert!(!nullable);
+    assert_eq!(pointee.span, span((1, 2), (1, 8)));
+    let (nullable, pointee) = pointer(pointee);
+    assert!(nullable);
+    assert_eq!(pointee.span, span((1, 4), (1, 8)));
+
+    assert_eq!(
+        parse_type("@;"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedType,
+            span: span((1, 2), (1, 3)),
+        })
+    );
+    assert_eq!(
+        parse_type("@?"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedType,
+            span: span((1, 3), (1, 3)),
+        })
+    );
+}
+
+#[test]
+pub fn test_optional_types() {
+    // Renders the type with any pointer or optional type in its own
+    // parentheses, to show how they nest.
+    fn rendered(r#type: &ast::TypeExpr) -> String {
+        match &r#type.kind {
+            ast::TypeExprKind::Named { path, .. } => path.segments[0].name.clone(),
+            ast::TypeExprKind::Optional(inner) => format!("({}?)", rendered(inner)),
+            ast::TypeExprKind::Pointer { nullable, pointee } => {
+                let nullable = if *nullable { "?" } else { "" };
+                format!("(@{nullable}{})", rendered(pointee))
+            }
+            ast::TypeExprKind::Array { element, length } => {
+                format!("({} * {})", rendered(element), grouped(length))
+            }
+            ast::TypeExprKind::Union(members) => {
+                let members: Vec<_> = members.iter().map(rendered).collect();
+                format!("({})", members.join(" + "))
+            }
+            ast::TypeExprKind::Function { params, ret } => {
+                let params: Vec<_> = params.iter().map(|param| rendered(&param.ty)).collect();
+                format!("(({}) -> {})", params.join(", "), rendered(ret))
+            }
+            kind => format!("{kind:?}"),
+        }
+    }
+
+    let cases = [
+        ("int?", "(int?)"),
+        ("@int?", "(@(int?))"),
+        ("(@int)?", "((@int)?)"),
+        ("@?int?", "(@?(int?))"),
+        ("(int * 4)?", "((int * 4)?)"),
+        ("(int? * 4)", "((int?) * 4)"),
+        ("(int32? + int64)?", "(((int32?) + int64)?)"),
+        ("(int) -> bool?", "((int) -> (bool?))"),
+        ("((int) -> bool)?", "(((int) -> bool)?)"),
+        ("Self?", "(Self?)"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(rendered(&parse_type(source).unwrap()), expected, "{source}");
+    }
+
+    let r#type = parse_type("(@int)?").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 8)));
+    let ast::TypeExprKind::Optional(inner) = r#type.kind else {
+        panic!("{:?}", r#type.kind);
+    };
+    assert_eq!(inner.span, span((1, 1), (1, 7)));
+
+    let (items, errors) = parse_items("module m; type Node = next: @Node?, data: int32?;");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Type(decl) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(rendered(&decl.fields[0].ty), "(@(Node?))");
+    assert_eq!(rendered(&decl.fields[1].ty), "(int32?)");
+}
+
+#[test]
+pub fn test_function_types() {
+    fn function(r#type: ast::TypeExpr) -> (Vec<ast::Param>, ast::TypeExpr) {
+        match r#type.kind {
+            ast::TypeExprKind::Function { params, ret } => (params, *ret),
+            kind => panic!("{kind:?}"),
+        }
+    }
+    fn named(r#type: &ast::TypeExpr) -> &str {
+        match &r#type.kind {
+            ast::TypeExprKind::Named { path, .. } => &path.segments[0].name,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    // The example's `field7` groups a function type in parentheses.
+    let r#type = parse_type("(() -> TypeType)").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 17)));
+    let (params, ret) = function(r#type);
+    assert!(params.is_empty());
+    assert_eq!(named(&ret), "TypeType");
+
+    let (params, ret) = function(parse_type("(int) -> bool").unwrap());
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].name, None);
+    assert_eq!(named(&params[0].ty), "int");
+    assert_eq!(named(&ret), "bool");
+
+    // The arrow is right-associative.
+    let r#type = parse_type("((int) -> (int) -> bool)").unwrap();
+    let (params, ret) = function(r#type);
+    assert_eq!(named(&params[0].ty), "int");
+    assert_eq!(ret.span, span((1, 11), (1, 24)));
+    let (params, ret) = function(ret);
+    assert_eq!(named(&params[0].ty), "int");
+    assert_eq!(named(&ret), "bool");
+
+    let (params, ret) = function(parse_type("( x: int, y: @bit8 ) -> int").unwrap());
+    assert_eq!(
+        params
+            .iter()
+            .map(|param| param.name.as_ref().unwrap().name.as_str())
+            .collect::<Vec<_>>(),
+        ["x", "y"]
+    );
+    assert_eq!(params[1].span, span((1, 11), (1, 19)));
+    assert!(matches!(
+        params[1].ty.kind,
+        ast::TypeExprKind::Pointer { .. }
+    ));
+    assert_eq!(named(&ret), "int");
+
+    assert_eq!(
+        parse_type("(() TypeType)"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::RightwardsArrow,
+                described("identifier", "TypeType"),
+                "in parenthesized type"
+            ),
+            span: span((1, 5), (1, 13)),
+        })
+    );
+}
+
+#[test]
+pub fn test_algebraic_types() {
+    fn algebraic(r#type: ast::TypeExpr) -> Vec<(ast::Ident, ast::TypeExpr)> {
+        match r#type.kind {
+            ast::TypeExprKind::Algebraic(variants) => variants,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let r#type = parse_type("(Identifier: String | Number: float64)").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 39)));
+    let variants = algebraic(r#type);
+    assert_eq!(variants.len(), 2);
+    assert_eq!(variants[0].0.name, "Identifier");
+    assert_eq!(variants[1].0.name, "Number");
+    assert_eq!(variants[1].0.span, span((1, 23), (1, 29)));
+    assert_eq!(variants[1].1.span, span((1, 31), (1, 38)));
+
+    let variants = algebraic(
+        parse_type("(Pair: (int, int) | Point: (x: int, y: int) | Ref: @?Node)").unwrap(),
+    );
+    assert_eq!(
+        variants
+            .iter()
+            .map(|(name, _)| name.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Pair", "Point", "Ref"]
+    );
+    assert!(matches!(variants[0].1.kind, ast::TypeExprKind::Tuple(_)));
+    assert!(matches!(
+        variants[1].1.kind,
+        ast::TypeExprKind::TaggedTuple(_)
+    ));
+    assert!(matches!(
+        variants[2].1.kind,
+        ast::TypeExprKind::Pointer { nullable: true, .. }
+    ));
+
+    assert_eq!(
+        parse_type("(A: int | B: bit | A: float)"),
+        Err(ParseError {
+            kind: ParseErrorKind::DuplicateVariant(String::from("A")),
+            span: span((1, 20), (1, 21)),
+        })
+    );
+
+    // Mixing plain and typed alternatives is an error whichever comes first.
+    assert_eq!(
+        parse_type("(Identifier: String | Empty)"),
+        Err(ParseError {
+            kind: ParseErrorKind::MissingPayload,
+            span: span((1, 23), (1, 28)),
+        })
+    );
+    assert_eq!(
+        parse_type("(Empty | Number: float64)"),
+        Err(ParseError {
+            kind: ParseErrorKind::MissingPayload,
+            span: span((1, 2), (1, 7)),
+        })
+    );
+}
+
+#[test]
+pub fn test_extend_blocks() {
+    fn extend(source: &str) -> (Option<ast::ExtendBlock>, Vec<ParseError>) {
+        let mut parser = Parser::new(Lexer::from_str(source));
+        let module = parser.parse_module().unwrap();
+        let extend = module.items.into_iter().next().map(|item| match item.kind {
+            ast::ItemKind::Extend(extend) => extend,
+            kind => panic!("{kind:?}"),
+        });
+        (extend, parser.errors().to_vec())
+    }
+    fn names(paths: &[ast::Path]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| {
+                let segments: Vec<_> = path.segments.iter().map(|segment| &*segment.name).collect();
+                segments.join("::")
+            })
+            .collect()
+    }
+
+    let (block, errors) = extend("module m;\nextend MyType\n    : ObjectType\n{\n}");
+    assert!(errors.is_empty());
+    let block = block.unwrap();
+    assert!(matches!(
+        block.target.kind,
+        ast::TypeExprKind::Named { ref path, .. } if path.segments[0].name == "MyType"
+    ));
+    assert_eq!(names(&block.traits), ["ObjectType"]);
+    assert!(block.items.is_empty());
+
+    let (block, errors) = extend("module m; extend (int, int) {}");
+    assert!(errors.is_empty());
+    let block = block.unwrap();
+    assert!(matches!(block.target.kind, ast::TypeExprKind::Tuple(_)));
+    assert!(block.traits.is_empty());
+
+    let (block, errors) = extend("module m; extend MyType: core::ObjectType, Copyable {}");
+    assert!(errors.is_empty());
+    assert_eq!(
+        names(&block.unwrap().traits),
+        ["core::ObjectType", "Copyable"]
+    );
+
+    // The block is kept, with the item that isn't allowed skipped.
+    let (block, errors) = extend("module m; extend MyType {\n    type Inner = x: int;\n}");
+    assert_eq!(
+        block.unwrap().items,
+        [ast::Item {
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Default,
+            kind: ast::ItemKind::Error,
+            span: span((2, 5), (2, 25)),
+        }]
+    );
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::ItemNotAllowed("extend block"),
+            span: span((2, 5), (2, 9)),
+        }]
+    );
+}
+
+#[cfg(test)]
+fn parse_items(source: &str) -> (Vec<ast::Item>, Vec<ParseError>) {
+    let mut parser = Parser::new(Lexer::from_str(source));
+    let module = parser.parse_module().unwrap();
+    (module.items, parser.errors().to_vec())
+}
+
+#[test]
+pub fn test_function_declarations() {
+    fn function(item: &ast::Item) -> &ast::FunctionDecl {
+        match &item.kind {
+            ast::ItemKind::Function(function) => function,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let (items, errors) = parse_items(
+        "module m;
+function destroy() -> Self = {};
+function add<T>(x: int, int,) -> int = x + 1;
+function do_something(Type) -> Type;
+function run()
+{
+    1;
+    x + 2;
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(items.len(), 4);
+
+    // An empty block is the expression body of `destroy`.
+    let destroy = function(&items[0]);
+    assert_eq!(destroy.name.name, "destroy");
+    assert!(destroy.params.is_empty());
+    assert!(destroy.return_type.is_some());
+    assert_eq!(
+        destroy.body,
+        Some(ast::FunctionBody::Expr(ast::Expr {
+            kind: ast::ExprKind::Block(ast::Block {
+                stmts: Vec::new(),
+                tail: None,
+                span: span((2, 30), (2, 32)),
+            }),
+            span: span((2, 30), (2, 32)),
+        }))
+    );
+    assert_eq!(items[0].span, span((2, 1), (2, 33)));
+
+    let add = function(&items[1]);
+    assert_eq!(add.generics[0].name, "T");
+    assert_eq!(add.params.len(), 2);
+    assert_eq!(add.params[0].name.as_ref().unwrap().name, "x");
+    assert_eq!(add.params[0].span, span((3, 17), (3, 23)));
+    assert_eq!(add.params[1].name, None);
+    assert!(matches!(
+        add.body,
+        Some(ast::FunctionBody::Expr(ast::Expr {
+            kind: ast::ExprKind::Binary { .. },
+            ..
+        }))
+    ));
+
+    // A signature without a body, as in a trait.
+    let signature = function(&items[2]);
+    assert_eq!(signature.params.len(), 1);
+    assert_eq!(signature.body, None);
+
+    let run = function(&items[3]);
+    assert_eq!(run.return_type, None);
+    let Some(ast::FunctionBody::Block(block)) = &run.body else {
+        panic!("{:?}", run.body);
+    };
+    assert_eq!(block.stmts.len(), 2);
+    assert_eq!(block.span, span((6, 1), (9, 2)));
+
+    let (items, errors) =
+        parse_items("module m; extend MyType { function destroy() -> Self = {}; }");
+    assert!(errors.is_empty());
+    let ast::ItemKind::Extend(block) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(function(&block.items[0]).name.name, "destroy");
+
+    let (_, errors) = parse_items("module m; function create() Self = 1;");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: expected_token(
+                TokenType::RightwardsArrow,
+                TokenDescription::Token(TokenType::UpperSelf),
+                "in function declaration"
+            ),
+            span: span((1, 29), (1, 33)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_value_declarations() {
+    fn value(item: &ast::Item) -> &ast::ValueDecl {
+        match &item.kind {
+            ast::ItemKind::Value(value) => value,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let (items, errors) = parse_items(
+        "module m;
+value x: int32 = 42;
+value y: bit8;
+value z = x + 1;
+value mutable counter = 0;
+value constant limit: int = 10;",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+
+    let x = value(&items[0]);
+    assert_eq!(x.mutability, ast::Mutability::Default);
+    assert_eq!(x.name.name, "x");
+    assert!(x.ty.is_some());
+    assert_eq!(
+        x.init,
+        Some(ast::Expr {
+            kind: ast::ExprKind::Literal(ast::Literal::Integer {
+                value: 42,
+                suffix: None,
+            }),
+            span: span((2, 18), (2, 20)),
+        })
+    );
+    assert_eq!(items[0].span, span((2, 1), (2, 21)));
+
+    let y = value(&items[1]);
+    assert!(y.ty.is_some());
+    assert_eq!(y.init, None);
+
+    let z = value(&items[2]);
+    assert_eq!(z.ty, None);
+    assert!(matches!(
+        z.init,
+        Some(ast::Expr {
+            kind: ast::ExprKind::Binary { .. },
+            ..
+        })
+    ));
+
+    let counter = value(&items[3]);
+    assert_eq!(counter.mutability, ast::Mutability::Mutable);
+    assert_eq!(counter.name.name, "counter");
+    assert_eq!(value(&items[4]).mutability, ast::Mutability::Constant);
+
+    let (items, errors) = parse_items("module m; function f() { value a = 1; a; }");
+    assert!(errors.is_empty());
+    let ast::ItemKind::Function(ast::FunctionDecl {
+        body: Some(ast::FunctionBody::Block(block)),
+        ..
+    }) = &items[0].kind
+    else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert!(matches!(block.stmts[0].kind, ast::StmtKind::Local(_)));
+    assert_eq!(block.stmts[0].span, span((1, 26), (1, 38)));
+
+    let (_, errors) = parse_items("module m; value nothing;");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::MissingTypeAndInitializer,
+            span: span((1, 17), (1, 24)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_type_declarations() {
+    let (items, errors) = parse_items(
+        "module m;
+type BitField: bit32 = bit1: bit
+                     , bit2: bit
+                     , bits3t5: (bit * 3)
+                     , bits6t8: bit3
+                     , bits: (bit * (32 - offset_of!(bits)));",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(items[0].span, span((2, 1), (6, 62)));
+    let ast::ItemKind::Type(decl) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(decl.name.name, "BitField");
+    assert!(decl.generics.is_empty());
+    let Some(ast::TypeExpr {
+        kind: ast::TypeExprKind::Named { path, args },
+        span: repr,
+    }) = &decl.repr
+    else {
+        panic!("{:?}", decl.repr);
+    };
+    assert_eq!(path.segments[0].name, "bit32");
+    assert!(args.is_empty());
+    assert_eq!(*repr, span((2, 16), (2, 21)));
+
+    // The fields stay in the order they're laid out in.
+    let names: Vec<_> = decl.fields.iter().map(|field| &*field.name.name).collect();
+    assert_eq!(names, ["bit1", "bit2", "bits3t5", "bits6t8", "bits"]);
+    assert_eq!(decl.fields[2].span, span((4, 24), (4, 42)));
+    assert_eq!(decl.fields[0].name.name, "bit1");
+    let ast::TypeExprKind::Array { element, length } = &decl.fields[2].ty.kind else {
+        panic!("{:?}", decl.fields[2].ty);
+    };
+    assert!(matches!(element.kind, ast::TypeExprKind::Named { .. }));
+    assert_eq!(grouped(length), "3");
+
+    let ast::TypeExprKind::Array { length, .. } = &decl.fields[4].ty.kind else {
+        panic!("{:?}", decl.fields[4].ty);
+    };
+    assert_eq!(grouped(length), "(32 - offset_of!(bits))");
+
+    let (items, errors) = parse_items("module m; type Pair<T> = first: T, second: T;");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Type(decl) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(decl.generics[0].name, "T");
+    assert_eq!(decl.repr, None);
+    assert_eq!(decl.fields.len(), 2);
+
+    // A type has at least one field, and each is named.
+    let (items, errors) = parse_items("module m; type Empty = ;");
+    assert_eq!(items[0].kind, ast::ItemKind::Error);
+    assert_eq!(errors[0].kind, ParseErrorKind::ExpectedIdentifier);
+    assert_eq!(errors[0].span, span((1, 24), (1, 25)));
+
+    let (_, errors) = parse_items("module m; type Bits: bit8 x: bit;");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: expected_token(
+                TokenType::EqualsSign,
+                described("identifier", "x"),
+                "in type declaration"
+            ),
+            span: span((1, 27), (1, 28)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_defaults() {
+    let (items, errors) = parse_items(
+        "module m;
+type Config = name: String, retries: int32 = 3, origin: (int, int) = (0, 0);
+function connect(host: String, port: int = 10) -> int;",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Type(config) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(config.fields[0].default, None);
+    assert_eq!(
+        config.fields[1].default.as_ref().map(grouped),
+        Some(String::from("3"))
+    );
+    assert_eq!(config.fields[1].span, span((2, 29), (2, 47)));
+    assert_eq!(
+        config.fields[2].default.as_ref().map(grouped),
+        Some(String::from("(0, 0)"))
+    );
+
+    let ast::ItemKind::Function(connect) = &items[1].kind else {
+        panic!("{:?}", items[1].kind);
+    };
+    assert_eq!(connect.params[0].default, None);
+    assert_eq!(
+        connect.params[1].default.as_ref().map(grouped),
+        Some(String::from("10"))
+    );
+    assert_eq!(connect.params[1].span, span((3, 32), (3, 46)));
+
+    // Everything after a default needs one too, which is reported without
+    // giving up on the rest.
+    let (items, errors) = parse_items(
+        "module m;
+function f(x: int = 1, y: int, z: int = 2) -> int;
+type Point = x: int = 0, y: int;",
+    );
+    assert!(items.iter().all(|item| item.kind != ast::ItemKind::Error));
+    assert_eq!(
+        errors,
+        [
+            ParseError {
+                kind: ParseErrorKind::RequiredAfterDefault,
+                span: span((2, 24), (2, 30)),
+            },
+            ParseError {
+                kind: ParseErrorKind::RequiredAfterDefault,
+                span: span((3, 26), (3, 32)),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "entry without a default after one with a default"
+    );
+
+    // Only declarations have defaults, not the fields of types within them.
+    let (_, errors) = parse_items("module m; type Pair = inner: (first: int = 1, second: int);");
+    assert!(!errors.is_empty());
+}
+
+#[test]
+pub fn test_alias_declarations() {
+    fn alias(item: &ast::Item) -> &ast::AliasDecl {
+        match &item.kind {
+            ast::ItemKind::Alias(alias) => alias,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let (items, errors) = parse_items(
+        "module m;
+trait MyTrait<TypeType>
+{
+    alias Type = TypeType;
+    alias Output;
+
+    function do_something(Type) -> Type;
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Trait(r#trait) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    assert_eq!(r#trait.name.name, "MyTrait");
+    assert_eq!(r#trait.generics[0].name, "TypeType");
+    assert_eq!(r#trait.items.len(), 3);
+    assert_eq!(alias(&r#trait.items[0]).name.name, "Type");
+    assert!(matches!(
+        alias(&r#trait.items[0]).target,
+        Some(ast::TypeExpr {
+            kind: ast::TypeExprKind::Named { .. },
+            ..
+        })
+    ));
+    assert_eq!(r#trait.items[0].span, span((4, 5), (4, 27)));
+    assert_eq!(alias(&r#trait.items[1]).target, None);
+
+    let (items, errors) = parse_items("module m; extend MyType { alias Pair<T> = (T, T); }");
+    assert!(errors.is_empty());
+    let ast::ItemKind::Extend(block) = &items[0].kind else {
+        panic!("{:?}", items[0].kind);
+    };
+    let pair = alias(&block.items[0]);
+    assert_eq!(pair.generics[0].name, "T");
+    assert!(matches!(
+        pair.target,
+        Some(ast::TypeExpr {
+            kind: ast::TypeExprKind::Tuple(_),
+            ..
+        })
+    ));
+
+    // An extend block has to say what the alias stands for.
+    let (_, errors) = parse_items("module m; extend MyType { alias Output; }");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: expected_token(
+                TokenType::EqualsSign,
+                TokenDescription::Token(TokenType::Semicolon),
+                "in alias declaration"
+            ),
+            span: span((1, 39), (1, 40)),
+        }]
+    );
+
+    let (items, errors) = parse_items("module m;\nalias Type = int;\nuse a;");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::ItemNotAllowed("module"),
+            span: span((2, 1), (2, 6)),
+        }]
+    );
+    assert!(matches!(
+        items[..],
+        [
+            ast::Item {
+                kind: ast::ItemKind::Error,
+                ..
+            },
+            ast::Item {
+                kind: ast::ItemKind::Use(_),
+                ..
+            }
+        ]
+    ));
+}
+
+#[cfg(test)]
+fn parse_expr(source: &str) -> Result<ast::Expr, ParseError> {
+    Parser::new(Lexer::from_str(source)).parse_expr()
+}
+
+// Writes an expression with its grouping made explicit, as in `(1 + (2 * 3))`.
+#[cfg(test)]
+fn grouped(expr: &ast::Expr) -> String {
+    match &expr.kind {
+        ast::ExprKind::Literal(ast::Literal::Integer { value, .. }) => value.to_string(),
+        ast::ExprKind::Path(path) => path.segments[0].name.clone(),
+        ast::ExprKind::Binary { op, left, right } => {
+            let op = match op {
+                ast::BinaryOp::Add => "+",
+                ast::BinaryOp::Subtract => "-",
+                ast::BinaryOp::Multiply => "*",
+                ast::BinaryOp::Divide => "/",
+                ast::BinaryOp::Remainder => "%",
+                ast::BinaryOp::ShiftLeft => "<<",
+                ast::BinaryOp::ShiftRight => ">>",
+                ast::BinaryOp::BitAnd => "&",
+                ast::BinaryOp::BitXor => "^",
+                ast::BinaryOp::BitOr => "|",
+                ast::BinaryOp::Equal => "==",
+                ast::BinaryOp::NotEqual => "!=",
+                ast::BinaryOp::Less => "<",
+                ast::BinaryOp::Greater => ">",
+                ast::BinaryOp::LessEqual => "<=",
+                ast::BinaryOp::GreaterEqual => ">=",
+                ast::BinaryOp::And => "&&",
+                ast::BinaryOp::Or => "||",
+                ast::BinaryOp::Range => "..",
+                ast::BinaryOp::RangeInclusive => "..=",
+            };
+            format!("({} {op} {})", grouped(left), grouped(right))
+        }
+        ast::ExprKind::Unary { op, operand } => {
+            let op = match op {
+                ast::UnaryOp::Negate => "-",
+                ast::UnaryOp::Not => "!",
+                ast::UnaryOp::AddressOf => "@",
+                ast::UnaryOp::Dereference => "*",
+            };
+            format!("({op}{})", grouped(operand))
+        }
+        ast::ExprKind::Field { base, name } => format!("{}.{}", grouped(base), name.name),
+        ast::ExprKind::Call { callee, args, .. } => {
+            let args: Vec<_> = args.iter().map(grouped).collect();
+            format!("{}({})", grouped(callee), args.join(", "))
+        }
+        ast::ExprKind::MacroCall(call) => {
+            let args: Vec<_> = call.args.iter().map(grouped).collect();
+            format!("{}!({})", call.name.name, args.join(", "))
+        }
+        ast::ExprKind::Tuple(elements) if elements.len() == 1 => {
+            format!("({},)", grouped(&elements[0]))
+        }
+        ast::ExprKind::Tuple(elements) => {
+            let elements: Vec<_> = elements.iter().map(grouped).collect();
+            format!("({})", elements.join(", "))
+        }
+        ast::ExprKind::TaggedTuple(fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|field| format!("{}: {}", field.name.name, grouped(&field.value)))
+                .collect();
+            format!("({})", fields.join(", "))
+        }
+        ast::ExprKind::Struct { ty, fields } => {
+            let ast::TypeExprKind::Named { path, .. } = &ty.kind else {
+                unreachable!();
+            };
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|field| format!("{}: {}", field.name.name, grouped(&field.value)))
+                .collect();
+            format!("{} {{{}}}", path.segments[0].name, fields.join(", "))
+        }
+        kind => format!("{kind:?}"),
+    }
+}
+
+#[test]
+pub fn test_operator_precedence() {
+    let cases = [
+        ("1 + 2 * 3", "(1 + (2 * 3))"),
+        ("(1 + 2) * 3", "((1 + 2) * 3)"),
+        ("a - b - c", "((a - b) - c)"),
+        ("a / b % c * d", "(((a / b) % c) * d)"),
+        ("a << 1 + b", "(a << (1 + b))"),
+        ("a & b ^ c | d", "(((a & b) ^ c) | d)"),
+        ("a + 1 == b * 2", "((a + 1) == (b * 2))"),
+        (
+            "a < b && c >= d || e != f",
+            "(((a < b) && (c >= d)) || (e != f))",
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    let expr = parse_expr("1 + 2 * 3").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 10)));
+    let ast::ExprKind::Binary { right, .. } = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(right.span, span((1, 5), (1, 10)));
+
+    // Whether this compares `a < b` with `c` or `a` with `b < c`, it isn't
+    // what it looks like, so it's rejected.
+    assert_eq!(
+        parse_expr("a < b < c"),
+        Err(ParseError {
+            kind: ParseErrorKind::ChainedComparison,
+            span: span((1, 7), (1, 8)),
+        })
+    );
+    assert_eq!(
+        grouped(&parse_expr("(a < b) < c").unwrap()),
+        "((a < b) < c)"
+    );
+
+    // Ranges take whole expressions at each end.
+    let cases = [
+        ("0..10", "(0 .. 10)"),
+        ("a + 1..=n * 2", "((a + 1) ..= (n * 2))"),
+        ("0..n || m", "(0 .. (n || m))"),
+        ("f(0..n)", "f((0 .. n))"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+    assert_eq!(
+        parse_expr("a..b..c"),
+        Err(ParseError {
+            kind: ParseErrorKind::ChainedRange,
+            span: span((1, 5), (1, 7)),
+        })
+    );
+}
+
+#[test]
+pub fn test_unary_expressions() {
+    let cases = [
+        ("-x", "(-x)"),
+        ("!flag", "(!flag)"),
+        ("@x", "(@x)"),
+        ("*pointer", "(*pointer)"),
+        ("--x", "(-(-x))"),
+        ("*@x", "(*(@x))"),
+        ("-a * b", "((-a) * b)"),
+        ("a - -b", "(a - (-b))"),
+        ("a * *b", "(a * (*b))"),
+        ("!a && !b", "((!a) && (!b))"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    let expr = parse_expr("a + -b").unwrap();
+    let ast::ExprKind::Binary { right, .. } = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(right.span, span((1, 5), (1, 7)));
+
+    assert_eq!(
+        parse_expr("!;"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedExpression,
+            span: span((1, 2), (1, 3)),
+        })
+    );
+
+    // A sign before a number is part of the literal, and is applied before
+    // it's range-checked.
+    let integer = |value| {
+        ast::ExprKind::Literal(ast::Literal::Integer {
+            value,
+            suffix: None,
+        })
+    };
+    let cases = [
+        ("-1", integer(-1)),
+        ("+1", integer(1)),
+        ("-9223372036854775808", integer(i64::MIN)),
+        (
+            "-1.5",
+            ast::ExprKind::Literal(ast::Literal::Decimal {
+                value: -1.5,
+                suffix: None,
+            }),
+        ),
+    ];
+    for (source, expected) in cases {
+        let expr = parse_expr(source).unwrap();
+        assert_eq!(expr.kind, expected, "{source}");
+        assert_eq!(expr.span.end.column as usize, source.len() + 1, "{source}");
+    }
+    assert_eq!(grouped(&parse_expr("- -1").unwrap()), "(--1)");
+    assert_eq!(grouped(&parse_expr("a-1").unwrap()), "(a - 1)");
+    for source in ["9223372036854775808", "+9223372036854775808"] {
+        assert_eq!(
+            parse_expr(source),
+            Err(ParseError {
+                kind: ParseErrorKind::LiteralOutOfRange {
+                    literal: source.to_string(),
+                    target: "int64",
+                },
+                span: span((1, 1), (1, source.len() as u32 + 1)),
+            })
+        );
+    }
+    let (_, errors) = parse_items("module m;\nvalue x: int = +1;\nvalue y = -1;");
+    assert!(errors.is_empty(), "{errors:?}");
+}
+
+#[test]
+pub fn test_interpolated_strings() {
+    let expr = parse_expr(r#""sum: \(a + b * 2)!""#).unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 21)));
+    let ast::ExprKind::InterpolatedString(parts) = &expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    let [ast::StringPart::Literal(before), ast::StringPart::Expr(sum), ast::StringPart::Literal(after)] =
+        &parts[..]
+    else {
+        panic!("{parts:?}");
+    };
+    assert_eq!((before.as_str(), after.as_str()), ("sum: ", "!"));
+    assert_eq!(grouped(sum), "(a + (b * 2))");
+    assert_eq!(sum.span, span((1, 9), (1, 18)));
+
+    // It's an expression like any other.
+    let (_, errors) = parse_items("module m;\nvalue s = f(\"\\(x.y) and \\(g(1))\");");
+    assert!(errors.is_empty(), "{errors:?}");
+
+    // Errors in the code are where they are in the string.
+    assert_eq!(
+        parse_expr(r#""\(a b)""#),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::RightParenthesis,
+                described("identifier", "b"),
+                "in string interpolation"
+            ),
+            span: span((1, 6), (1, 7)),
+        })
+    );
+    assert_eq!(
+        parse_expr(r#""\(a +)""#).unwrap_err().kind,
+        ParseErrorKind::ExpectedExpression
+    );
+}
+
+#[test]
+pub fn test_call_expressions() {
+    let cases = [
+        ("f()", "f()"),
+        ("f(x)", "f(x)"),
+        ("f(a, b + 1, c,)", "f(a, (b + 1), c)"),
+        ("f(g(x), h())", "f(g(x), h())"),
+        ("f(x)(y)", "f(x)(y)"),
+        ("!f(x)", "(!f(x))"),
+        ("-f() * 2", "((-f()) * 2)"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    let expr = parse_expr("f(g(x), h())").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 13)));
+    let ast::ExprKind::Call { args, .. } = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(args[0].span, span((1, 3), (1, 7)));
+
+    for source in ["f(a, b", "f(a b)", "f(g(x)"] {
+        assert_eq!(
+            parse_expr(source),
+            Err(ParseError {
+                kind: ParseErrorKind::UnclosedDelimiter,
+                span: span((1, 2), (1, 3)),
+            }),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+pub fn test_field_access() {
+    let cases = [
+        ("a.b", "a.b"),
+        ("a.b.c.d", "a.b.c.d"),
+        ("self.field1", "self.field1"),
+        ("self.copy()", "self.copy()"),
+        ("a.b().c()", "a.b().c()"),
+        ("f(x).y", "f(x).y"),
+        ("pair.0", "pair.0"),
+        ("pair.0.1", "pair.0.1"),
+        ("pair.1.name.0", "pair.1.name.0"),
+        ("-a.b", "(-a.b)"),
+        ("a.b * c.d", "(a.b * c.d)"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    // `0.1` is one token, but each index still gets its own span.
+    let expr = parse_expr("pair.0.1").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 9)));
+    let ast::ExprKind::Field { base, name } = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(name.span, span((1, 8), (1, 9)));
+    assert_eq!(base.span, span((1, 1), (1, 7)));
+
+    let ast::ExprKind::Call { callee, .. } = parse_expr("a.b()").unwrap().kind else {
+        panic!();
+    };
+    assert!(matches!(callee.kind, ast::ExprKind::Field { .. }));
+
+    for source in ["pair.1e3", "pair.0int8", "pair.0x1"] {
+        assert_eq!(
+            parse_expr(source).unwrap_err().kind,
+            ParseErrorKind::InvalidTupleIndex,
+            "{source}"
+        );
+    }
+    assert_eq!(
+        parse_expr("a.;"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 3), (1, 4)),
+        })
+    );
+}
+
+#[test]
+pub fn test_assignment() {
+    let stmt = |source: &str| {
+        let source = format!("{{ {source} }}");
+        let mut parser = Parser::new(Lexer::new(source.chars()));
+        let mut block = parser.parse_block()?;
+        match parser.errors().first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(block.stmts.remove(0)),
+        }
+    };
+
+    let ast::StmtKind::Assign { target, op, value } = stmt("x = 1;").unwrap().kind else {
+        panic!();
+    };
+    assert_eq!(grouped(&target), "x");
+    assert_eq!(op, None);
+    assert_eq!(grouped(&value), "1");
+
+    let assign = stmt("self.field1 = (21, 14);").unwrap();
+    assert_eq!(assign.span, span((1, 3), (1, 26)));
+    let ast::StmtKind::Assign { target, .. } = assign.kind else {
+        panic!();
+    };
+    assert_eq!(grouped(&target), "self.field1");
+
+    let ast::StmtKind::Assign { target, op, value } = stmt("*p += a * 2;").unwrap().kind else {
+        panic!();
+    };
+    assert_eq!(grouped(&target), "(*p)");
+    assert_eq!(op, Some(ast::BinaryOp::Add));
+    assert_eq!(grouped(&value), "(a * 2)");
+
+    for (source, expected) in [
+        ("x -= 1;", ast::BinaryOp::Subtract),
+        ("x *= 1;", ast::BinaryOp::Multiply),
+        ("x /= 1;", ast::BinaryOp::Divide),
+        ("x %= 1;", ast::BinaryOp::Remainder),
+        ("x ^= 1;", ast::BinaryOp::BitXor),
+    ] {
+        let ast::StmtKind::Assign { op, .. } = stmt(source).unwrap().kind else {
+            panic!("{source}");
+        };
+        assert_eq!(op, Some(expected), "{source}");
+    }
+
+    assert_eq!(
+        stmt("1 + 2 = x;"),
+        Err(ParseError {
+            kind: ParseErrorKind::InvalidAssignmentTarget,
+            span: span((1, 3), (1, 8)),
+        })
+    );
+    assert_eq!(
+        stmt("f() = x;").unwrap_err().kind,
+        ParseErrorKind::InvalidAssignmentTarget
+    );
+
+    // Assignment is a statement, so it doesn't chain.
+    assert_eq!(
+        stmt("a = b = c;"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                TokenDescription::Token(TokenType::EqualsSign),
+                "after assignment"
+            ),
+            span: span((1, 9), (1, 10)),
+        })
+    );
+}
+
+#[test]
+pub fn test_return_statements() {
+    let block = |source: &str| {
+        let mut parser = Parser::new(Lexer::new(source.chars()));
+        let block = parser.parse_block().unwrap();
+        (block.stmts, parser.errors().to_vec())
+    };
+
+    let (stmts, errors) = block("{ return; }");
+    assert!(errors.is_empty());
+    assert_eq!(
+        stmts,
+        vec![ast::Stmt {
+            kind: ast::StmtKind::Return(None),
+            span: span((1, 3), (1, 10)),
+        }]
+    );
+
+    let (stmts, errors) = block("{ f(x); return a + b; }");
+    assert!(errors.is_empty());
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(stmts[1].span, span((1, 9), (1, 22)));
+    let ast::StmtKind::Return(Some(value)) = &stmts[1].kind else {
+        panic!("{:?}", stmts[1]);
+    };
+    assert_eq!(grouped(value), "(a + b)");
+
+    let (stmts, errors) = block("{ return (21, 14); }");
+    assert!(errors.is_empty());
+    let ast::StmtKind::Return(Some(value)) = &stmts[0].kind else {
+        panic!("{:?}", stmts[0]);
+    };
+    assert_eq!(grouped(value), "(21, 14)");
+
+    // Without its `;`, the return still ends where its value does and the
+    // statement after it still parses.
+    let (stmts, errors) = block("{ return x\n f(); }");
+    assert_eq!(
+        errors,
+        vec![ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                described("identifier", "f"),
+                "in return statement"
+            ),
+            span: span((2, 2), (2, 3)),
+        }]
+    );
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(stmts[0].span, span((1, 3), (1, 11)));
+    assert!(matches!(stmts[1].kind, ast::StmtKind::Expr(_)));
+
+    let (stmts, errors) = block("{ return }");
+    assert_eq!(stmts[0].kind, ast::StmtKind::Return(None));
+    assert_eq!(
+        errors,
+        vec![ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                TokenDescription::Token(TokenType::RightCurlyBracket),
+                "in return statement"
+            ),
+            span: span((1, 10), (1, 11)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_local_values() {
+    let block = |source: &str| {
+        let mut parser = Parser::new(Lexer::new(source.chars()));
+        let block = parser.parse_block().unwrap();
+        (block.stmts, parser.errors().to_vec())
+    };
+    fn local(stmt: &ast::Stmt) -> &ast::ValueDecl {
+        match &stmt.kind {
+            ast::StmtKind::Local(decl) => decl,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let (stmts, errors) = block(
+        "{
+    value x = f();
+    value mutable y: int32 = 1;
+    value z = if x { 1 } else { 2 };
+    value w = { g(); y };
+    value x = x + 1;
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(stmts.len(), 5);
+    assert_eq!(local(&stmts[0]).ty, None);
+    assert_eq!(stmts[0].span, span((2, 5), (2, 19)));
+    assert_eq!(local(&stmts[1]).mutability, ast::Mutability::Mutable);
+    assert!(local(&stmts[1]).ty.is_some());
+    assert!(matches!(
+        local(&stmts[2]).init,
+        Some(ast::Expr {
+            kind: ast::ExprKind::If { .. },
+            ..
+        })
+    ));
+    let Some(ast::Expr {
+        kind: ast::ExprKind::Block(init),
+        ..
+    }) = &local(&stmts[3]).init
+    else {
+        panic!("{:?}", stmts[3]);
+    };
+    assert_eq!(init.tail.as_deref().map(grouped), Some(String::from("y")));
+
+    // Shadowing is left for later to allow, so the same name parses twice.
+    assert_eq!(local(&stmts[0]).name.name, "x");
+    assert_eq!(local(&stmts[4]).name.name, "x");
+
+    let (stmts, errors) = block("{ value x; f(); }");
+    assert_eq!(stmts[0].kind, ast::StmtKind::Error);
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::MissingTypeAndInitializer,
+            span: span((1, 9), (1, 10)),
+        }]
+    );
+    assert!(matches!(stmts[1].kind, ast::StmtKind::Expr(_)));
+
+    let (_, errors) = block("{ value x = ; }");
+    assert_eq!(errors[0].kind, ParseErrorKind::ExpectedExpression);
+
+    // A missing `;` is only reported, keeping the declaration.
+    let (stmts, errors) = block("{ value x = 1\n  f(); }");
+    assert_eq!(local(&stmts[0]).name.name, "x");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                described("identifier", "f"),
+                "in value declaration"
+            ),
+            span: span((2, 3), (2, 4)),
+        }]
+    );
+    assert_eq!(stmts.len(), 2);
+}
+
+#[test]
+pub fn test_loops() {
+    let block = |source: &str| {
+        let mut parser = Parser::new(Lexer::new(source.chars()));
+        let block = parser.parse_block().unwrap();
+        (block.stmts, parser.errors().to_vec())
+    };
+
+    let (stmts, errors) = block("{ while count < 10 { count += 1; } }");
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(stmts[0].span, span((1, 3), (1, 35)));
+    let ast::StmtKind::While { condition, body } = &stmts[0].kind else {
+        panic!("{:?}", stmts[0]);
+    };
+    assert_eq!(grouped(condition), "(count < 10)");
+    assert_eq!(body.stmts.len(), 1);
+
+    let (stmts, errors) = block("{ for i: int in items { f(i); } }");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::StmtKind::For {
+        binding,
+        ty,
+        iterable,
+        body,
+    } = &stmts[0].kind
+    else {
+        panic!("{:?}", stmts[0]);
+    };
+    assert!(matches!(&binding.kind, ast::PatternKind::Binding(name) if name.name == "i"));
+    assert_eq!(binding.span, span((1, 7), (1, 8)));
+    assert!(matches!(
+        ty,
+        Some(ast::TypeExpr {
+            kind: ast::TypeExprKind::Named { .. },
+            ..
+        })
+    ));
+    assert_eq!(grouped(iterable), "items");
+    assert_eq!(body.stmts.len(), 1);
+
+    let (stmts, errors) = block("{ for i: int in 0..10 { } }");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::StmtKind::For { iterable, body, .. } = &stmts[0].kind else {
+        panic!("{:?}", stmts[0]);
+    };
+    assert_eq!(grouped(iterable), "(0 .. 10)");
+    assert!(body.stmts.is_empty());
+
+    let (stmts, errors) = block("{ for (key, _) in pairs { g(key); } }");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::StmtKind::For { binding, ty, .. } = &stmts[0].kind else {
+        panic!("{:?}", stmts[0]);
+    };
+    assert!(matches!(&binding.kind, ast::PatternKind::Tuple(elements) if elements.len() == 2));
+    assert_eq!(*ty, None);
+
+    // Nested, with `break` and `continue` wherever they're written.
+    let (stmts, errors) = block(
+        "{
+    break;
+    for row in rows {
+        while true {
+            if done(row) { break; }
+            continue;
+        }
+    }
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(stmts[0].kind, ast::StmtKind::Break);
+    assert_eq!(stmts[0].span, span((2, 5), (2, 11)));
+    let ast::StmtKind::For { body, .. } = &stmts[1].kind else {
+        panic!("{:?}", stmts[1]);
+    };
+    let ast::StmtKind::While { body, .. } = &body.stmts[0].kind else {
+        panic!("{:?}", body.stmts[0]);
+    };
+    assert_eq!(body.stmts[1].kind, ast::StmtKind::Continue);
+
+    let (stmts, errors) = block("{ break }");
+    assert_eq!(stmts[0].kind, ast::StmtKind::Break);
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                TokenDescription::Token(TokenType::RightCurlyBracket),
+                "after break"
+            ),
+            span: span((1, 9), (1, 10)),
+        }]
+    );
+    let (_, errors) = block("{ while x f(); }");
+    assert_eq!(
+        errors[0].kind,
+        expected_token(
+            TokenType::LeftCurlyBracket,
+            described("identifier", "f"),
+            "in while loop"
+        )
+    );
+    let (_, errors) = block("{ for x of xs {} }");
+    assert_eq!(
+        errors[0],
+        ParseError {
+            kind: expected_token(TokenType::In, described("identifier", "of"), "in for loop"),
+            span: span((1, 9), (1, 11)),
+        }
+    );
+}
+
+#[test]
+pub fn test_blocks() {
+    let block = |source: &str| {
+        let mut parser = Parser::new(Lexer::new(source.chars()));
+        let block = parser.parse_block()?;
+        match parser.errors().first() {
+            Some(error) => Err(error.clone()),
+            None => Ok(block),
+        }
+    };
+
+    let empty = block("{}").unwrap();
+    assert!(empty.stmts.is_empty());
+    assert_eq!(empty.span, span((1, 1), (1, 3)));
+
+    // Three deep, with a statement after each inner block.
+    let outer = block("{ { { f(); } g(); } h(); }").unwrap();
+    assert_eq!(outer.span, span((1, 1), (1, 27)));
+    assert_eq!(outer.stmts.len(), 2);
+    let ast::StmtKind::Expr(ast::Expr {
+        kind: ast::ExprKind::Block(middle),
+        span: middle_span,
+    }) = &outer.stmts[0].kind
+    else {
+        panic!("{:?}", outer.stmts[0]);
+    };
+    assert_eq!(*middle_span, span((1, 3), (1, 20)));
+    assert_eq!(middle.stmts.len(), 2);
+    let ast::StmtKind::Expr(ast::Expr {
+        kind: ast::ExprKind::Block(inner),
+        ..
+    }) = &middle.stmts[0].kind
+    else {
+        panic!("{:?}", middle.stmts[0]);
+    };
+    assert_eq!(inner.span, span((1, 5), (1, 13)));
+    assert_eq!(inner.stmts.len(), 1);
+
+    // The last expression without a `;` is the block's value, but any other
+    // still needs one.
+    let value = block("{ f(); x }").unwrap();
+    assert_eq!(value.stmts.len(), 1);
+    assert_eq!(value.tail.as_deref().map(grouped), Some(String::from("x")));
+    assert_eq!(block("{ x; }").unwrap().tail, None);
+    assert_eq!(
+        block("{ x y }"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::Semicolon,
+                described("identifier", "y"),
+                "after expression"
+            ),
+            span: span((1, 5), (1, 6)),
+        })
+    );
+
+    // An unclosed block is reported once, at its brace.
+    let (items, errors) =
+        parse_items("module m;\nfunction f() {\n    value x = 1;\n    { g(); }\n");
+    assert!(matches!(
+        items[..],
+        [ast::Item {
+            kind: ast::ItemKind::Error,
+            ..
+        }]
+    ));
+    assert_eq!(
+        errors,
+        vec![ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((2, 14), (2, 15)),
+        }]
+    );
+
+    // So are nested ones, at the innermost brace, the blocks around it
+    // having hit the end of the file too.
+    let (_, errors) = parse_items("module m;\nfunction f() { { {\n");
+    assert_eq!(
+        errors,
+        vec![ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((2, 18), (2, 19)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_if_expressions() {
+    fn if_parts(expr: &ast::Expr) -> (&ast::Expr, &ast::Block, Option<&ast::Expr>) {
+        match &expr.kind {
+            ast::ExprKind::If {
+                condition,
+                then_block,
+                else_branch,
+            } => (condition, then_block, else_branch.as_deref()),
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let expr = parse_expr("if x < 1 { f(); }").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 18)));
+    let (condition, then_block, else_branch) = if_parts(&expr);
+    assert_eq!(grouped(condition), "(x < 1)");
+    assert_eq!(then_block.stmts.len(), 1);
+    assert_eq!(else_branch, None);
+
+    let expr = parse_expr("if ready { 1 } else { 2 }").unwrap();
+    let (_, then_block, else_branch) = if_parts(&expr);
+    assert_eq!(
+        then_block.tail.as_deref().map(grouped),
+        Some(String::from("1"))
+    );
+    let Some(ast::Expr {
+        kind: ast::ExprKind::Block(block),
+        span: else_span,
+    }) = else_branch
+    else {
+        panic!("{else_branch:?}");
+    };
+    assert_eq!(block.tail.as_deref().map(grouped), Some(String::from("2")));
+    assert_eq!(*else_span, span((1, 21), (1, 26)));
+
+    // Each `else if` nests in the `else` of the one before.
+    let expr =
+        parse_expr("if a { f(); } else if b { g(); } else if c { h(); } else { i(); }").unwrap();
+    let mut conditions = Vec::new();
+    let mut branch = Some(&expr);
+    while let Some(
+        expr @ ast::Expr {
+            kind: ast::ExprKind::If { .. },
+            ..
+        },
+    ) = branch
+    {
+        let (condition, _, else_branch) = if_parts(expr);
+        conditions.push(grouped(condition));
+        branch = else_branch;
+    }
+    assert_eq!(conditions, ["a", "b", "c"]);
+    assert!(matches!(
+        branch,
+        Some(ast::Expr {
+            kind: ast::ExprKind::Block(_),
+            ..
+        })
+    ));
+
+    // The `{` after a name in a condition starts the block, not a struct
+    // literal, unless it's in parentheses.
+    let expr = parse_expr("if point == (Point { x: 1 }) { f(); }").unwrap();
+    assert_eq!(grouped(if_parts(&expr).0), "(point == Point {x: 1})");
+
+    let (items, errors) = parse_items(
+        "module m;
+function f(c: bool) -> int
+{
+    value x = if c { 1 } else { 2 };
+    if x == 1 { g(); }
+    h();
+    if c { x } else { 3 }
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Function(function) = &items[0].kind else {
+        panic!("{:?}", items[0]);
+    };
+    let Some(ast::FunctionBody::Block(body)) = &function.body else {
+        panic!("{:?}", function.body);
+    };
+    assert_eq!(body.stmts.len(), 3);
+    let ast::StmtKind::Local(ast::ValueDecl {
+        init: Some(init), ..
+    }) = &body.stmts[0].kind
+    else {
+        panic!("{:?}", body.stmts[0]);
+    };
+    assert_eq!(grouped(if_parts(init).0), "c");
+    assert_eq!(body.stmts[1].span, span((5, 5), (5, 23)));
+    assert!(matches!(
+        body.tail.as_deref(),
+        Some(ast::Expr {
+            kind: ast::ExprKind::If { .. },
+            ..
+        })
+    ));
+
+    assert_eq!(
+        parse_expr("if c f();"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::LeftCurlyBracket,
+                described("identifier", "f"),
+                "in if expression"
+            ),
+            span: span((1, 6), (1, 7)),
+        })
+    );
+    assert_eq!(
+        parse_expr("if a { f(); } else g();").unwrap_err().span,
+        span((1, 20), (1, 21))
+    );
+}
+
+#[test]
+pub fn test_match_expressions() {
+    fn match_arms(expr: &ast::Expr) -> &[ast::MatchArm] {
+        match &expr.kind {
+            ast::ExprKind::Match { arms, .. } => arms,
+            kind => panic!("{kind:?}"),
+        }
+    }
+
+    let expr = parse_expr(
+        "match token {
+    Identifier: name => lookup(name),
+    Number: 0 => zero,
+    Number: _ => other,
+}",
+    )
+    .unwrap();
+    assert_eq!(expr.span, span((1, 1), (5, 2)));
+    let ast::ExprKind::Match { scrutinee, .. } = &expr.kind else {
+        panic!();
+    };
+    assert_eq!(grouped(scrutinee), "token");
+    let arms = match_arms(&expr);
+    assert_eq!(arms.len(), 3);
+    assert_eq!(arms[0].span, span((2, 5), (2, 37)));
+    let ast::PatternKind::Variant { name, pattern } = &arms[0].pattern.kind else {
+        panic!("{:?}", arms[0].pattern);
+    };
+    assert_eq!(name.name, "Identifier");
+    assert!(matches!(&pattern.kind, ast::PatternKind::Binding(name) if name.name == "name"));
+    assert_eq!(grouped(&arms[0].body), "lookup(name)");
+    assert!(matches!(
+        &arms[1].pattern.kind,
+        ast::PatternKind::Variant { pattern, .. }
+            if matches!(pattern.kind, ast::PatternKind::Literal(ast::Literal::Integer { value: 0, .. }))
+    ));
+    assert!(matches!(
+        &arms[2].pattern.kind,
+        ast::PatternKind::Variant { pattern, .. } if pattern.kind == ast::PatternKind::Wildcard
+    ));
+
+    // Without a trailing comma, and with tuples like in expressions.
+    let expr = parse_expr("match pair { (0, _) => 1, (x, (y,)) => x, () => 2 }").unwrap();
+    let arms = match_arms(&expr);
+    let ast::PatternKind::Tuple(elements) = &arms[0].pattern.kind else {
+        panic!("{:?}", arms[0].pattern);
+    };
+    assert_eq!(elements.len(), 2);
+    assert_eq!(elements[1].kind, ast::PatternKind::Wildcard);
+    assert_eq!(arms[0].pattern.span, span((1, 14), (1, 20)));
+    let ast::PatternKind::Tuple(elements) = &arms[1].pattern.kind else {
+        panic!("{:?}", arms[1].pattern);
+    };
+    assert!(matches!(&elements[1].kind, ast::PatternKind::Tuple(inner) if inner.len() == 1));
+    assert_eq!(arms[2].pattern.kind, ast::PatternKind::Tuple(Vec::new()));
+    let expr = parse_expr("match x { (y) => y }").unwrap();
+    assert!(matches!(
+        match_arms(&expr)[0].pattern.kind,
+        ast::PatternKind::Binding(_)
+    ));
+
+    // A match in an arm, and one on its own as a statement.
+    let (items, errors) = parse_items(
+        "module m;
+function f(a: int, b: int) -> int
+{
+    match a { _ => g(), }
+    match a {
+        0 => match b { 0 => 1, _ => 2 },
+        _ => { 3 },
+    }
+}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Function(function) = &items[0].kind else {
+        panic!("{:?}", items[0]);
+    };
+    let Some(ast::FunctionBody::Block(body)) = &function.body else {
+        panic!("{:?}", function.body);
+    };
+    assert_eq!(body.stmts.len(), 1);
+    let outer = body.tail.as_deref().unwrap();
+    let inner = &match_arms(outer)[0].body;
+    assert_eq!(match_arms(inner).len(), 2);
+    assert_eq!(inner.span, span((6, 14), (6, 40)));
+
+    assert_eq!(
+        parse_expr("match x { 1 -> a }"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::RightwardsDoubleArrow,
+                TokenDescription::Token(TokenType::RightwardsArrow),
+                "in match arm"
+            ),
+            span: span((1, 13), (1, 15)),
+        })
+    );
+    assert_eq!(
+        parse_expr("match x { 1 => a 2 => b }").unwrap_err().kind,
+        ParseErrorKind::Expected {
+            expected: vec![
+                TokenDescription::Token(TokenType::Comma),
+                TokenDescription::Token(TokenType::RightCurlyBracket),
+            ],
+            found: described("number", "2"),
+            context: "in match expression",
+        }
+    );
+    assert_eq!(
+        parse_expr("match x { + => a }"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedPattern,
+            span: span((1, 11), (1, 12)),
+        })
+    );
+}
+
+#[test]
+pub fn test_parenthesized_expressions() {
+    let cases = [
+        ("(x + 1)", "(x + 1)"),
+        ("((x))", "x"),
+        ("()", "()"),
+        ("(x,)", "(x,)"),
+        ("(21, 14)", "(21, 14)"),
+        ("(a, (b, c),)", "(a, (b, c))"),
+        ("(first: 1, second: 2)", "(first: 1, second: 2)"),
+        ("(first: (1, 2),)", "(first: (1, 2))"),
+        ("f((a, b), c)", "f((a, b), c)"),
+        ("(a, b).0", "(a, b).0"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    // Grouping parentheses widen the span of what's inside, but keep its kind.
+    let expr = parse_expr("(x + 1)").unwrap();
+    assert!(matches!(expr.kind, ast::ExprKind::Binary { .. }));
+    assert_eq!(expr.span, span((1, 1), (1, 8)));
+
+    let ast::ExprKind::TaggedTuple(fields) = parse_expr("(first: 1, second: x)").unwrap().kind
+    else {
+        panic!();
+    };
+    assert_eq!(fields[1].name.name, "second");
+    assert_eq!(fields[1].span, span((1, 12), (1, 21)));
+
+    assert_eq!(
+        parse_expr("(a + b: 1)"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 2), (1, 7)),
+        })
+    );
+    // A tagged tuple is tagged throughout.
+    assert_eq!(
+        parse_expr("(first: 1, 2)"),
+        Err(ParseError {
+            kind: ParseErrorKind::ExpectedIdentifier,
+            span: span((1, 12), (1, 13)),
+        })
+    );
+    assert_eq!(
+        parse_expr("(a, b"),
+        Err(ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((1, 1), (1, 2)),
+        })
+    );
+}
+
+#[test]
+pub fn test_generic_arguments() {
+    fn rendered(r#type: &ast::TypeExpr) -> String {
+        match &r#type.kind {
+            ast::TypeExprKind::Named { path, args } if args.is_empty() => {
+                path.segments[0].name.clone()
+            }
+            ast::TypeExprKind::Named { path, args } => {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|arg| match arg {
+                        ast::GenericArg::Type(r#type) => rendered(r#type),
+                        ast::GenericArg::Const(expr) => grouped(expr),
+                    })
+                    .collect();
+                format!("{}<{}>", path.segments[0].name, args.join(", "))
+            }
+            kind => format!("{kind:?}"),
+        }
+    }
+
+    let cases = [
+        ("MyType<TypeType>", "MyType<TypeType>"),
+        ("Map<String, int>", "Map<String, int>"),
+        ("Map<String, List<int>>", "Map<String, List<int>>"),
+        ("List<List<List<int>>>", "List<List<List<int>>>"),
+        ("Array<int, 32>", "Array<int, 32>"),
+        ("Array<int, -1>", "Array<int, -1>"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(rendered(&parse_type(source).unwrap()), expected, "{source}");
+    }
+
+    // The halves of a `>>` end their own types.
+    let r#type = parse_type("Map<String, List<int>>").unwrap();
+    assert_eq!(r#type.span, span((1, 1), (1, 23)));
+    let ast::TypeExprKind::Named { args, .. } = r#type.kind else {
+        panic!();
+    };
+    let ast::GenericArg::Type(inner) = &args[1] else {
+        panic!("{:?}", args[1]);
+    };
+    assert_eq!(inner.span, span((1, 13), (1, 22)));
+
+    let (items, errors) = parse_items("module m;\nextend MyType<TypeType> : ObjectType {}");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Extend(block) = &items[0].kind else {
+        panic!("{:?}", items[0]);
+    };
+    assert_eq!(rendered(&block.target), "MyType<TypeType>");
+
+    assert_eq!(
+        parse_type("List<int;"),
+        Err(ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((1, 5), (1, 6)),
+        })
+    );
+    assert_eq!(
+        parse_type("List<>").unwrap_err().kind,
+        ParseErrorKind::ExpectedExpression
+    );
+}
+
+#[test]
+pub fn test_generic_calls() {
+    let ast::ExprKind::Call {
+        callee,
+        generics,
+        args,
+    } = parse_expr("f<int>(x)").unwrap().kind
+    else {
+        panic!();
+    };
+    assert_eq!(grouped(&callee), "f");
+    assert!(matches!(
+        &generics[..],
+        [ast::GenericArg::Type(ast::TypeExpr {
+            kind: ast::TypeExprKind::Named { .. },
+            ..
+        })]
+    ));
+    assert_eq!(args.len(), 1);
+
+    let expr = parse_expr("create<Map<String, int>, 32>()").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 31)));
+    let ast::ExprKind::Call { generics, .. } = expr.kind else {
+        panic!("{expr:?}");
+    };
+    assert!(matches!(generics[1], ast::GenericArg::Const(_)));
+
+    // Where it's either, the `(` straight after the `>` makes it a call.
+    let ast::ExprKind::Call { generics, args, .. } = parse_expr("a < b > (c)").unwrap().kind else {
+        panic!();
+    };
+    assert_eq!(generics.len(), 1);
+    assert_eq!(grouped(&args[0]), "c");
+
+    let cases = [
+        ("a < b", "(a < b)"),
+        ("a < b + 1", "(a < (b + 1))"),
+        ("a < b && c > (d)", "((a < b) && (c > d))"),
+        ("a < b >> (c)", "(a < (b >> c))"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    // Otherwise it's a chain of comparisons, which isn't allowed.
+    for source in ["a < b > c", "f(x) < g > (y)"] {
+        assert_eq!(
+            parse_expr(source).unwrap_err().kind,
+            ParseErrorKind::ChainedComparison,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+pub fn test_struct_literals() {
+    let cases = [
+        ("Self {}", "Self {}"),
+        (
+            "Self { field1: (1, 2), field2: x }",
+            "Self {field1: (1, 2), field2: x}",
+        ),
+        ("Self { field1: 1, }", "Self {field1: 1}"),
+        (
+            "Self { field1, field2: 2 }",
+            "Self {field1: field1, field2: 2}",
+        ),
+        ("Point { x: Point {}.x }", "Point {x: Point {}.x}"),
+        ("Self {}.copy()", "Self {}.copy()"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    let expr = parse_expr("core::Pair { first }").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 21)));
+    let ast::ExprKind::Struct { ty, fields } = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(ty.span, span((1, 1), (1, 11)));
+    assert_eq!(fields[0].span, span((1, 14), (1, 19)));
+
+    // `Self::create` is still a path.
+    let ast::ExprKind::Path(path) = parse_expr("Self::create").unwrap().kind else {
+        panic!();
+    };
+    assert_eq!(path.segments.len(), 2);
+
+    let (items, errors) = parse_items("module m;\nfunction create() -> Self = Self {};");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Function(function) = &items[0].kind else {
+        panic!("{:?}", items[0]);
+    };
+    assert!(matches!(
+        function.body,
+        Some(ast::FunctionBody::Expr(ast::Expr {
+            kind: ast::ExprKind::Struct { .. },
+            ..
+        }))
+    ));
+
+    // At the start of a statement, a name followed by `{` is a struct literal
+    // rather than a name and then a block.
+    let mut parser = Parser::new(Lexer::from_str("{ x {}; }"));
+    let block = parser.parse_block().unwrap();
+    assert!(matches!(
+        &block.stmts[0].kind,
+        ast::StmtKind::Expr(ast::Expr {
+            kind: ast::ExprKind::Struct { .. },
+            ..
+        })
+    ));
+    let mut parser = Parser::new(Lexer::from_str("{ x { f(); } }"));
+    parser.parse_block().unwrap();
+    assert_eq!(
+        parser.errors(),
+        [ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((1, 5), (1, 6)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_receivers() {
+    let function = |source: &str| {
+        let (mut items, errors) = parse_items(&format!("module m;\n{source}"));
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+        match items.remove(0).kind {
+            ast::ItemKind::Function(function) => function,
+            kind => panic!("{kind:?}"),
+        }
+    };
+
+    let cases = [
+        (
+            "function copy(self) -> Self;",
+            false,
+            false,
+            span((2, 15), (2, 19)),
+        ),
+        ("function f(@self);", true, false, span((2, 12), (2, 17))),
+        (
+            "function do_something(@?self) -> Self;",
+            true,
+            true,
+            span((2, 23), (2, 29)),
+        ),
+    ];
+    for (source, pointer, nullable, expected) in cases {
+        let function = function(source);
+        assert_eq!(
+            function.receiver,
+            Some(ast::Receiver {
+                pointer,
+                nullable,
+                span: expected,
+            }),
+            "{source}"
+        );
+        assert!(function.params.is_empty(), "{source}");
+    }
+
+    let with_params = function("function add(@self, other: @Self, @?int);");
+    assert!(with_params.receiver.is_some());
+    assert_eq!(with_params.params.len(), 2);
+
+    // A pointer that isn't to `self` is just a parameter.
+    let free = function("function free(@?bit8, count: int);");
+    assert_eq!(free.receiver, None);
+    assert_eq!(free.params.len(), 2);
+
+    for (source, expected) in [
+        ("function f(x: int, self);", span((2, 20), (2, 24))),
+        ("function f(self, @?self);", span((2, 18), (2, 24))),
+    ] {
+        let (_, errors) = parse_items(&format!("module m;\n{source}"));
+        assert_eq!(
+            errors,
+            [ParseError {
+                kind: ParseErrorKind::MisplacedReceiver,
+                span: expected,
+            }],
+            "{source}"
+        );
+    }
+}
+
+#[test]
+pub fn test_attributes() {
+    let (items, errors) =
+        parse_items("module m;\nderive!(Debuggable)\nextend MyType<TypeType> : ObjectType {}");
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(items[0].span, span((2, 1), (3, 40)));
+    let [derive] = &items[0].attributes[..] else {
+        panic!("{:?}", items[0].attributes);
+    };
+    assert_eq!(derive.name.name, "derive");
+    assert_eq!(derive.name.span, span((2, 1), (2, 8)));
+    assert_eq!(derive.span, span((2, 1), (2, 20)));
+    assert_eq!(grouped(&derive.args[0]), "Debuggable");
+    assert!(matches!(items[0].kind, ast::ItemKind::Extend(_)));
+
+    let (items, errors) = parse_items(
+        "module m;\nderive!(Debuggable, core::Copyable)\nlayout!(packed)\nfunction f();\nfunction g();",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let names: Vec<_> = items[0]
+        .attributes
+        .iter()
+        .map(|attribute| attribute.name.name.as_str())
+        .collect();
+    assert_eq!(names, ["derive", "layout"]);
+    assert_eq!(items[0].attributes[0].args.len(), 2);
+    assert!(items[1].attributes.is_empty());
+
+    // Items in blocks take attributes too.
+    let (items, errors) = parse_items("module m;\nextend T {\n    inline!() function f();\n}");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ast::ItemKind::Extend(block) = &items[0].kind else {
+        panic!();
+    };
+    assert_eq!(block.items[0].attributes[0].name.name, "inline");
+    assert_eq!(block.items[0].span, span((3, 5), (3, 28)));
+
+    for (source, expected) in [
+        ("module m;\nderive!(Debuggable)", span((2, 1), (2, 20))),
+        (
+            "module m;\nextend T {\n    derive!(a) derive!(b)\n}",
+            span((3, 16), (3, 26)),
+        ),
+    ] {
+        let (_, errors) = parse_items(source);
+        assert_eq!(
+            errors,
+            [ParseError {
+                kind: ParseErrorKind::DanglingAttribute,
+                span: expected,
+            }],
+            "{source}"
+        );
+    }
+}
+
+#[test]
+pub fn test_visibility() {
+    let (items, errors) = parse_items(
+        "module m;
+public type Point = x: int, y: int;
+type Hidden = x: int;
+private trait Shape { public function area() -> int; alias Unit; }
+public function f() {}
+function g() {}
+private value limit = 10;
+value count = 0;
+extend Point { private alias Coordinate = int; alias Other = int; }",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    let visibilities: Vec<_> = items.iter().map(|item| item.visibility).collect();
+    assert_eq!(
+        visibilities,
+        [
+            ast::Visibility::Public,
+            ast::Visibility::Default,
+            ast::Visibility::Private,
+            ast::Visibility::Public,
+            ast::Visibility::Default,
+            ast::Visibility::Private,
+            ast::Visibility::Default,
+            ast::Visibility::Default,
+        ]
+    );
+    assert_eq!(items[0].span, span((2, 1), (2, 36)));
+    let ast::ItemKind::Trait(shape) = &items[2].kind else {
+        panic!("{:?}", items[2].kind);
+    };
+    assert_eq!(shape.items[0].visibility, ast::Visibility::Public);
+    assert_eq!(shape.items[0].span, span((4, 23), (4, 53)));
+    assert_eq!(shape.items[1].visibility, ast::Visibility::Default);
+    let ast::ItemKind::Extend(block) = &items[7].kind else {
+        panic!("{:?}", items[7].kind);
+    };
+    assert_eq!(block.items[0].visibility, ast::Visibility::Private);
+    assert_eq!(block.items[1].visibility, ast::Visibility::Default);
+
+    // Attributes come before the visibility, and the item spans both.
+    let (items, errors) = parse_items(
+        "module m;
+derive!(Debuggable)
+public type T = x: int;",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(items[0].visibility, ast::Visibility::Public);
+    assert_eq!(items[0].span, span((2, 1), (3, 24)));
+
+    let (items, errors) = parse_items(
+        "module m;
+public use core::ObjectType;
+public public function f();
+use a;",
+    );
+    assert_eq!(
+        errors,
+        [
+            ParseError {
+                kind: ParseErrorKind::VisibilityNotAllowed("use declaration"),
+                span: span((2, 1), (2, 7)),
+            },
+            ParseError {
+                kind: ParseErrorKind::DuplicateVisibility,
+                span: span((3, 8), (3, 14)),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "visibility not allowed on use declaration"
+    );
+    // Only the visibilities are skipped, leaving the items after them.
+    assert_eq!(items.len(), 5);
+    assert_eq!(items[0].kind, ast::ItemKind::Error);
+    assert_eq!(items[0].span, span((2, 1), (2, 7)));
+    assert!(matches!(items[1].kind, ast::ItemKind::Use(_)));
+    assert_eq!(items[2].kind, ast::ItemKind::Error);
+    assert!(matches!(items[3].kind, ast::ItemKind::Function(_)));
+
+    let (_, errors) = parse_items(
+        "module m;
+private extend T {}",
+    );
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::VisibilityNotAllowed("extend block")
+    );
+    let (_, errors) = parse_items(
+        "module m;
+public 1;",
+    );
+    assert_eq!(
+        errors[0].kind,
+        ParseErrorKind::Expected {
+            expected: [
+                TokenType::Type,
+                TokenType::Trait,
+                TokenType::Function,
+                TokenType::Value
+            ]
+            .map(TokenDescription::Token)
+            .to_vec(),
+            found: described("number", "1"),
+            context: "in module",
+        }
+    );
+
+    assert_eq!(
+        parse_expr("f(public x)"),
+        Err(ParseError {
+            kind: ParseErrorKind::VisibilityNotAllowed("expression"),
+            span: span((1, 3), (1, 9)),
+        })
+    );
+    let (_, errors) = parse_items("module m; function f() { public value x = 1; }");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::VisibilityNotAllowed("local value"),
+            span: span((1, 26), (1, 32)),
+        }]
+    );
+}
+
+#[test]
+pub fn test_macro_calls() {
+    let cases = [
+        ("offset_of!(bits)", "offset_of!(bits)"),
+        ("size_of!()", "size_of!()"),
+        ("32 - offset_of!(bits)", "(32 - offset_of!(bits))"),
+        (
+            "max!(size_of!(a), size_of!(b))",
+            "max!(size_of!(a), size_of!(b))",
+        ),
+        ("-count!(a).0", "(-count!(a).0)"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(grouped(&parse_expr(source).unwrap()), expected, "{source}");
+    }
+
+    let expr = parse_expr("offset_of!(bits)").unwrap();
+    assert_eq!(expr.span, span((1, 1), (1, 17)));
+    let ast::ExprKind::MacroCall(call) = expr.kind else {
+        panic!("{:?}", expr.kind);
+    };
+    assert_eq!(call.name.name, "offset_of");
+    assert_eq!(call.span, expr.span);
+
+    let ast::TypeExprKind::Array { length, .. } =
+        parse_type("(bit * 32 - offset_of!(bits))").unwrap().kind
+    else {
+        panic!();
+    };
+    assert_eq!(grouped(&length), "(32 - offset_of!(bits))");
+
+    let mut parser = Parser::new(Lexer::from_str("{ print!(x, y); }"));
+    let block = parser.parse_block().unwrap();
+    assert!(matches!(
+        &block.stmts[0].kind,
+        ast::StmtKind::Expr(ast::Expr {
+            kind: ast::ExprKind::MacroCall(_),
+            ..
+        })
+    ));
+
+    assert_eq!(
+        parse_expr("offset_of!(bits"),
+        Err(ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((1, 11), (1, 12)),
+        })
+    );
+    assert_eq!(
+        parse_expr("offset_of! + 1"),
+        Err(ParseError {
+            kind: expected_token(
+                TokenType::LeftParenthesis,
+                TokenDescription::Token(TokenType::PlusSign),
+                "in macro call"
+            ),
+            span: span((1, 12), (1, 13)),
+        })
+    );
+}
+
+#[test]
+pub fn test_item_list() {
+    let (items, errors) = parse_items(
+        "module m;
+## The first.
+value a = 1;
+#[ Between them. ]#
+derive!(Debuggable)
+## The second.
+public type B = x: int;
+function c() {}",
+    );
+    assert!(errors.is_empty(), "{errors:?}");
+    assert_eq!(items.len(), 3);
+    assert!(matches!(items[0].kind, ast::ItemKind::Value(_)));
+    assert_eq!(items[1].attributes.len(), 1);
+    assert_eq!(items[1].span, span((5, 1), (7, 24)));
+    assert!(matches!(items[2].kind, ast::ItemKind::Function(_)));
+
+    // A run of stray tokens between items is reported once.
+    let (items, errors) = parse_items(
+        "module m;
+value a = 1;
+; ; ) 1
+value b = 2;;",
+    );
+    assert_eq!(
+        errors,
+        [
+            ParseError {
+                kind: ParseErrorKind::ExpectedItem,
+                span: span((3, 1), (3, 2)),
+            },
+            ParseError {
+                kind: ParseErrorKind::ExpectedItem,
+                span: span((4, 13), (4, 14)),
+            },
+        ]
+    );
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[1].kind, ast::ItemKind::Error);
+    assert_eq!(items[1].span, span((3, 1), (3, 8)));
+    assert!(matches!(items[2].kind, ast::ItemKind::Value(_)));
+    assert_eq!(items[3].span, span((4, 13), (4, 14)));
+
+    let (items, errors) = parse_items(
+        "module m;
+## Nothing after it.
+",
+    );
+    assert!(items.is_empty());
+    assert!(errors.is_empty());
+}
+
+#[test]
+pub fn test_parser_error_recovery() {
+    let source = "module m;
+use a::{b c};
+function f() -> int = 1;
+value x: = 2;
+function g(a: int) { return a +; }";
+    let (items, errors) = parse_items(source);
+    let kinds: Vec<_> = errors.iter().map(|error| error.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [
+            ParseErrorKind::Expected {
+                expected: vec![
+                    TokenDescription::Token(TokenType::Comma),
+                    TokenDescription::Token(TokenType::RightCurlyBracket),
+                ],
+                found: described("identifier", "c"),
+                context: "in use declaration",
+            },
+            ParseErrorKind::ExpectedType,
+            ParseErrorKind::ExpectedExpression,
+        ]
+    );
+
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[0].kind, ast::ItemKind::Error);
+    assert_eq!(items[0].span, span((2, 1), (2, 14)));
+    assert!(matches!(items[1].kind, ast::ItemKind::Function(_)));
+    assert_eq!(items[2].kind, ast::ItemKind::Error);
+    assert_eq!(items[2].span, span((4, 1), (4, 14)));
+
+    // An error in a statement only loses that statement.
+    let ast::ItemKind::Function(ast::FunctionDecl {
+        body: Some(ast::FunctionBody::Block(block)),
+        ..
+    }) = &items[3].kind
+    else {
+        panic!("{:?}", items[3]);
+    };
+    assert_eq!(
+        block.stmts,
+        [ast::Stmt {
+            kind: ast::StmtKind::Error,
+            span: span((5, 22), (5, 33)),
+        }]
+    );
+
+    let (items, errors) = parse_items("module m;\n) use a;");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::ExpectedItem,
+            span: span((2, 1), (2, 2)),
+        }]
+    );
+    assert!(matches!(items[1].kind, ast::ItemKind::Use(_)));
+
+    // A missing `}` is reported at its `{`, and the next item still parses.
+    let (items, errors) = parse_items("module m;\nfunction f() {\n    a();\nfunction g() {}");
+    assert_eq!(
+        errors,
+        [ParseError {
+            kind: ParseErrorKind::UnclosedDelimiter,
+            span: span((2, 14), (2, 15)),
+        }]
+    );
+    assert_eq!(items[0].kind, ast::ItemKind::Error);
+    assert!(matches!(items[1].kind, ast::ItemKind::Function(_)));
+}
+
+#[test]
+pub fn test_parse_error_messages() {
+    let message = |source: &str| {
+        let mut parser = Parser::new(Lexer::from_str(source));
+        parser.parse_module();
+        parser.errors()[0].to_string()
+    };
+
+    assert_eq!(
+        message("module m;\nuse core::{ObjectType Copyable};"),
+        "expected ',' or '}' in use declaration, found identifier `Copyable`"
+    );
+    assert_eq!(
+        message("module m;\nfunction f() -> int 1"),
+        "expected '{', '=' or ';' in function declaration, found number `1`"
+    );
+    assert_eq!(
+        message("module m;\nfunction f() { a = b = c; }"),
+        "expected ';' after assignment, found '='"
+    );
+    assert_eq!(
+        message(""),
+        "expected 'module' at the start of the file, found end of file"
+    );
+    assert_eq!(
+        message("module m;\nextend T { x }"),
+        "expected 'function', 'value', 'alias' or '}' in extend block, found identifier `x`"
+    );
+    assert_eq!(
+        message("module m;\nuse type;"),
+        "expected identifier, found keyword 'type'"
+    );
+}
+
+#[test]
+pub fn test_parse() {
+    let source = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/example_program.xi"
+    ))
+    .unwrap();
+
+    // Only the `#` notes in the type declaration and the `Self =` after a
+    // `return` aren't understood.
+    let (module, diagnostics) = Parser::parse(&source);
+    assert_eq!(
+        diagnostics,
+        [
+            Diagnostic {
+                span: span((16, 73), (16, 74)),
+                message: String::from("expected ';' in type declaration, found '#'"),
+            },
+            Diagnostic {
+                span: span((39, 21), (39, 22)),
+                message: String::from("expected ';' in return statement, found '='"),
+            },
+        ]
+    );
+    let module = module.unwrap();
+    assert_eq!(module.items.len(), 6);
+    let ast::ItemKind::Extend(block) = &module.items[5].kind else {
+        panic!("{:?}", module.items[5]);
+    };
+    assert_eq!(block.items.len(), 3);
+
+    // Both kinds of error come out together, in the order they're in.
+    let (module, diagnostics) = Parser::parse("module m;\nvalue x = 1; `\nuse a::{b c};");
+    assert!(module.is_some());
+    assert_eq!(
+        diagnostics,
+        [
+            Diagnostic {
+                span: span((2, 14), (2, 15)),
+                message: String::from("unknown token"),
+            },
+            Diagnostic {
+                span: span((3, 11), (3, 12)),
+                message: String::from(
+                    "expected ',' or '}' in use declaration, found identifier `c`"
+                ),
+            },
+        ]
+    );
+
+    let (module, diagnostics) = Parser::parse("use a;");
+    assert_eq!(module, None);
+    assert_eq!(diagnostics.len(), 1);
+}
+
+#[test]
+pub fn test_token_stream() {
+    let source = "## A value.\nvalue x = 1;";
+    let one = TokenType::Integer {
+        value: 1,
+        suffix: None,
+    };
+    let types = |source| {
+        let mut tokens = TokenStream::new(Lexer::from_str(source));
+        let mut types = Vec::new();
+        while !tokens.at(&TokenType::EndOfFile) {
+            types.push(tokens.next().r#type);
+        }
+        types
+    };
+
+    // Looking ahead doesn't lose or repeat any tokens.
+    let mut tokens = TokenStream::new(Lexer::from_str(source));
+    assert_eq!(tokens.peek_nth(3).r#type, one.clone());
+    assert_eq!(tokens.peek_nth(1).r#type, identifier("x"));
+    assert_eq!(tokens.peek().r#type, TokenType::Value);
+    let mut consumed = Vec::new();
+    while !tokens.at(&TokenType::EndOfFile) {
+        consumed.push(tokens.next().r#type);
+    }
+    assert_eq!(consumed, types(source));
+    assert_eq!(
+        consumed,
+        [
+            TokenType::Value,
+            identifier("x"),
+            TokenType::EqualsSign,
+            one.clone(),
+            TokenType::Semicolon,
+        ]
+    );
+    assert_eq!(tokens.previous_end(), &Position { row: 2, column: 13 });
+
+    // Past the end, it's the end of the file however far it looks.
+    assert_eq!(tokens.peek_nth(5).r#type, TokenType::EndOfFile);
+    assert_eq!(tokens.next().r#type, TokenType::EndOfFile);
+    assert_eq!(tokens.peek().r#type, TokenType::EndOfFile);
+
+    assert!(tokens.eat(&TokenType::Semicolon).is_none());
+    let error = tokens
+        .expect(&[TokenType::Semicolon], "in test")
+        .unwrap_err();
+    assert_eq!(error.to_string(), "expected ';' in test, found end of file");
+    assert!(tokens.trivia().is_empty());
+
+    // Doc comments are left out, unless asked to be kept.
+    let mut tokens = TokenStream::with_trivia(Lexer::from_str(source));
+    assert_eq!(tokens.next().r#type, TokenType::Value);
+    assert_eq!(tokens.trivia().len(), 1);
+    assert_eq!(
+        tokens.trivia()[0].r#type,
+        TokenType::DocComment(Cow::Borrowed("A value."))
+    );
 }
@@ -1,5 +1,20 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Position {
-    pub row: usize,
-    pub column: usize,
+    pub row: u32,
+    // Starts at 1.
+    pub column: u32,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+    pub start: Position,
+    // One past the last character.
+    pub end: Position,
+}
+
+// A problem with the source, described for whoever wrote it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
 }
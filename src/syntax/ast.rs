@@ -0,0 +1,444 @@
+use crate::{
+    diagnostics::Span,
+    syntax::token::{CharWidth, NumericSuffix},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+// A name made of `::`-separated segments, like `core::ObjectType`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Path {
+    pub segments: Vec<Ident>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Module {
+    pub name: Path,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+//
+// Items
+//
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Item {
+    // The macros before it, like `derive!(Debuggable)`.
+    pub attributes: Vec<MacroCall>,
+    pub visibility: Visibility,
+    pub kind: ItemKind,
+    pub span: Span,
+}
+
+// Whether an item can be used outside its module, where leaving it out lets
+// the item's kind decide.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Visibility {
+    Default,
+    Public,  // 'public'
+    Private, // 'private'
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ItemKind {
+    Type(TypeDecl),
+    Trait(TraitDecl),
+    Extend(ExtendBlock),
+    Function(FunctionDecl),
+    Value(ValueDecl),
+    Use(UseDecl),
+    Alias(AliasDecl),
+    // What was skipped over after a syntax error.
+    Error,
+}
+
+// `type MyType<TypeType> = field1: (int, int), field2: bit8;`, with the
+// fields in the order they're laid out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeDecl {
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    // The type the fields are packed into, like the `bit32` in
+    // `type BitField: bit32 = ...`.
+    pub repr: Option<TypeExpr>,
+    pub fields: Vec<Field>,
+}
+
+// A `name: Type` pair, as in a type declaration or a tagged tuple.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    pub name: Ident,
+    pub ty: TypeExpr,
+    // The value after `=`, which only a type declaration's fields can have.
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraitDecl {
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    pub items: Vec<Item>,
+}
+
+// `extend MyType<TypeType> : ObjectType { ... }`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExtendBlock {
+    pub target: TypeExpr,
+    pub traits: Vec<Path>,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionDecl {
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    pub receiver: Option<Receiver>,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeExpr>,
+    // Left out for a signature, as in a trait.
+    pub body: Option<FunctionBody>,
+}
+
+// The `self` a function can take first: `self`, `@self`, or `@?self` for a
+// pointer to it that can be null.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Receiver {
+    pub pointer: bool,
+    pub nullable: bool,
+    pub span: Span,
+}
+
+// A parameter is either a bare type or a `name: Type` pair.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Param {
+    pub name: Option<Ident>,
+    pub ty: TypeExpr,
+    // The value after `=`, which only a function declaration's parameters
+    // can have.
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FunctionBody {
+    Block(Block),
+    // The expression after `=`, as in `function create() -> Self = Self {};`.
+    Expr(Expr),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValueDecl {
+    pub mutability: Mutability,
+    pub name: Ident,
+    pub ty: Option<TypeExpr>,
+    pub init: Option<Expr>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mutability {
+    Default,
+    Mutable,
+    Constant,
+}
+
+// `alias Type = TypeType;`, where a trait can leave out the type to have it
+// given by whatever extends the trait.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AliasDecl {
+    pub name: Ident,
+    pub generics: Vec<Ident>,
+    pub target: Option<TypeExpr>,
+}
+
+// `use core::ObjectType as Object;`, or `use core::{ObjectType, Copyable};`
+// where `path` is the `core` before the group.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UseDecl {
+    pub path: Path,
+    pub kind: UseKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum UseKind {
+    Single {
+        // The name after `as`.
+        alias: Option<Ident>,
+    },
+    Group(Vec<UseDecl>),
+}
+
+// `derive!(Debuggable)`, either before an item or as an expression, with the
+// name not including the `!`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MacroCall {
+    pub name: Ident,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+//
+// Types
+//
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeExpr {
+    pub kind: TypeExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeExprKind {
+    // `int`, `Self` or `core::ObjectType<TypeType>`.
+    Named {
+        path: Path,
+        args: Vec<GenericArg>,
+    },
+    Tuple(Vec<TypeExpr>),    // '(int, int)'
+    TaggedTuple(Vec<Field>), // '(first: int, second: int)'
+    Enumeration(Vec<Ident>), // '(Monday | Tuesday)'
+    // `(int * 32)`, where the length is any constant expression, like
+    // `32 - offset_of!(bits)`.
+    Array {
+        element: Box<TypeExpr>,
+        length: Box<Expr>,
+    },
+    Union(Vec<TypeExpr>), // '(int32 + int64)'
+    // `@bit8`, or `@?bit8` for one that can be null.
+    Pointer {
+        nullable: bool,
+        pointee: Box<TypeExpr>,
+    },
+    // `(int) -> bool`, or `(x: int) -> bool` with named parameters.
+    Function {
+        params: Vec<Param>,
+        ret: Box<TypeExpr>,
+    },
+    Algebraic(Vec<(Ident, TypeExpr)>), // '(Identifier: String | Number: float64)'
+    // `int32?`, which binds tighter than `@`, so `@int?` points to an
+    // optional `int` and `(@int)?` is an optional pointer.
+    Optional(Box<TypeExpr>),
+}
+
+// An argument in the `<>` after a type's name.
+#[derive(Debug, PartialEq, Clone)]
+pub enum GenericArg {
+    Type(TypeExpr),
+    // A constant, like the `32` in `Array<int, 32>`.
+    Const(Expr),
+}
+
+//
+// Statements
+//
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    // The expression at the end without a `;`, whose value is the block's.
+    pub tail: Option<Box<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StmtKind {
+    // An expression followed by `;`.
+    Expr(Expr),
+    // A `value` declared in a block, which unlike one at the top of a module
+    // can shadow another of the same name.
+    Local(ValueDecl),
+    // `target = value;`, or `target += value;` with `op` as the `+`.
+    Assign {
+        target: Expr,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    Return(Option<Expr>),
+    While {
+        condition: Expr,
+        body: Block,
+    },
+    // `for i: int in items { ... }`, where the binding can also destructure,
+    // as in `for (key, value) in pairs { ... }`.
+    For {
+        binding: Pattern,
+        ty: Option<TypeExpr>,
+        iterable: Expr,
+        body: Block,
+    },
+    Break,
+    Continue,
+    // What was skipped over after a syntax error.
+    Error,
+}
+
+//
+// Expressions
+//
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExprKind {
+    Literal(Literal),
+    Path(Path),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    // `f(x)`, or `f<int>(x)` with generic arguments.
+    Call {
+        callee: Box<Expr>,
+        generics: Vec<GenericArg>,
+        args: Vec<Expr>,
+    },
+    Field {
+        base: Box<Expr>,
+        name: Ident,
+    },
+    // `()`, `(x,)` or `(21, 14)`; a single element without the comma is just
+    // grouping.
+    Tuple(Vec<Expr>),
+    TaggedTuple(Vec<FieldValue>), // '(first: 1, second: 2)'
+    // `Self { field1: (1, 2), field2: x }`.
+    Struct {
+        ty: TypeExpr,
+        fields: Vec<FieldValue>,
+    },
+    MacroCall(MacroCall), // 'offset_of!(bits)'
+    Block(Block),
+    // `if c { 1 } else { 2 }`, where the `else` branch is either a block or
+    // another `if`.
+    If {
+        condition: Box<Expr>,
+        then_block: Block,
+        else_branch: Option<Box<Expr>>,
+    },
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+    // `"sum: \(a + b)"`, with the code of each interpolation parsed.
+    InterpolatedString(Vec<StringPart>),
+}
+
+// A piece of an interpolated string.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart {
+    Literal(String),
+    Expr(Expr),
+}
+
+// A `name: value` pair, as in a tagged tuple or a struct literal.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldValue {
+    pub name: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
+// `pattern => expression`, as in a match.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal {
+    Bits {
+        value: u64,
+        suffix: Option<NumericSuffix>,
+    },
+    Integer {
+        value: i64,
+        suffix: Option<NumericSuffix>,
+    },
+    Decimal {
+        value: f64,
+        suffix: Option<NumericSuffix>,
+    },
+    Boolean(bool),
+    String(String),
+    Character {
+        value: char,
+        width: Option<CharWidth>,
+    },
+    Null,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryOp {
+    Negate,      // '-'
+    Not,         // '!'
+    AddressOf,   // '@'
+    Dereference, // '*'
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOp {
+    Add,            // '+'
+    Subtract,       // '-'
+    Multiply,       // '*'
+    Divide,         // '/'
+    Remainder,      // '%'
+    ShiftLeft,      // '<<'
+    ShiftRight,     // '>>'
+    BitAnd,         // '&'
+    BitXor,         // '^'
+    BitOr,          // '|'
+    Equal,          // '=='
+    NotEqual,       // '!='
+    Less,           // '<'
+    Greater,        // '>'
+    LessEqual,      // '<='
+    GreaterEqual,   // '>='
+    And,            // '&&'
+    Or,             // '||'
+    Range,          // '..'
+    RangeInclusive, // '..='
+}
+
+//
+// Patterns
+//
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatternKind {
+    Literal(Literal),
+    // A name the matched value is bound to.
+    Binding(Ident),
+    Wildcard, // '_'
+    // `()`, `(x,)` or `(x, _)`, like a tuple expression.
+    Tuple(Vec<Pattern>),
+    // `Identifier: name`, matching one alternative of an algebraic type.
+    Variant { name: Ident, pattern: Box<Pattern> },
+}
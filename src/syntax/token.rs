@@ -78,43 +78,117 @@ extend MyType<TypeType>
 
 */
 
-use crate::diagnostics::Position;
+use crate::{
+    compiler::parser::KEYWORDS,
+    diagnostics::{Position, Span},
+};
+use std::borrow::Cow;
 
-#[derive(Debug)]
-pub struct Token {
-    pub r#type: TokenType,
-    position: Position,
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token<'a> {
+    pub r#type: TokenType<'a>,
+    pub(crate) span: Span,
+    pub(crate) lexeme: Cow<'a, str>,
 }
 
-impl Token {
+impl<'a> Token<'a> {
     pub fn new(position: Position) -> Self {
         Self {
             r#type: TokenType::None,
-            position,
+            span: Span {
+                start: position.clone(),
+                end: position,
+            },
+            lexeme: Cow::Borrowed(""),
         }
     }
 
-    pub fn r#type(&self) -> &TokenType {
+    pub fn r#type(&self) -> &TokenType<'a> {
         &self.r#type
     }
+
+    pub fn position(&self) -> &Position {
+        &self.span.start
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            r#type: self.r#type.into_owned(),
+            span: self.span,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+        }
+    }
+
+    // Splits a `>>` back into its two `>`s, such as where it closes nested
+    // generics like `List<List<int>>`.
+    pub fn split_double_right_angle(&self) -> Option<(Token<'a>, Token<'a>)> {
+        if self.r#type != TokenType::DoubleRightAngle {
+            return None;
+        }
+
+        let middle = Position {
+            row: self.span.start.row,
+            column: self.span.start.column + 1,
+        };
+        let mut first = Token::new(self.span.start.clone());
+        first.r#type = TokenType::RightAngleBracket;
+        first.span.end = middle.clone();
+        first.lexeme = Cow::Borrowed(">");
+
+        let mut second = Token::new(middle);
+        second.r#type = TokenType::RightAngleBracket;
+        second.span.end = self.span.end.clone();
+        second.lexeme = Cow::Borrowed(">");
+
+        Some((first, second))
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum TokenType {
+pub enum TokenType<'a> {
     None,
+    EndOfFile,
 
-    Identifier(String),
+    Identifier(Cow<'a, str>),
+    MacroIdentifier(Cow<'a, str>), // An identifier immediately followed by '!'
+    DocComment(Cow<'a, str>),      // The rest of a line after '##'
 
     // Literals
-    Bits(u64),
-    Integer(i64),
-    Decimal(f64),
+    //
+    // Plain decimal digits like `21` or `+21` are always an Integer. Only the
+    // radix forms `0x`, `0b` and `0o`, or a `bit` suffix as in `21bit`, make
+    // a Bits, so the kind of a number never depends on whether it's signed.
+    Bits {
+        value: u64,
+        suffix: Option<NumericSuffix>,
+    },
+    // Without signed literals, `9223372036854775808` comes through as
+    // `i64::MIN`, the one magnitude that's in range only once negated, and
+    // the parser checks it when it sees the sign.
+    Integer {
+        value: i64,
+        suffix: Option<NumericSuffix>,
+    },
+    Decimal {
+        value: f64,
+        suffix: Option<NumericSuffix>,
+    },
     Boolean(bool),
-    String(String),
-    Character(char),
-    BitsLiteral,
-    IntegerLiteral,
-    DecimalLiteral,
+    String(Cow<'a, str>),
+    InterpolatedString(Vec<StringPart<'a>>),
+    Character {
+        value: char,
+        width: Option<CharWidth>,
+    },
+    Null,
 
     //
     // Words
@@ -127,10 +201,35 @@ pub enum TokenType {
     Extend,   // 'extend'
     Function, // 'function'
     Value,    // 'value'
+    Alias,    // 'alias'
 
     // Actional Words
     Use,    // 'use'
     Return, // 'return'
+    As,     // 'as'
+
+    // Control Words
+    If,       // 'if'
+    Else,     // 'else'
+    Match,    // 'match'
+    While,    // 'while'
+    For,      // 'for'
+    In,       // 'in'
+    Loop,     // 'loop'
+    Break,    // 'break'
+    Continue, // 'continue'
+
+    // Visibility Words
+    Public,  // 'public'
+    Private, // 'private'
+
+    // Mutability Words
+    Mutable,  // 'mutable'
+    Constant, // 'constant'
+
+    // Referential Words
+    LowerSelf, // 'self'
+    UpperSelf, // 'Self'
 
     // Valuable Words
     True,
@@ -141,59 +240,87 @@ pub enum TokenType {
     //
 
     // Bit Types
-    Bit,   // 'bit'
-    Bit8,  // 'bit8'
-    Bit16, // 'bit16'
-    Bit32, // 'bit32'
-    Bit64, // 'bit64'
+    Bit,       // 'bit'
+    Bit8,      // 'bit8'
+    Bit16,     // 'bit16'
+    Bit32,     // 'bit32'
+    Bit64,     // 'bit64'
+    BitN(u16), // 'bit' followed by any other width
 
     // Integer Types
-    Int,   // 'int'
-    Int8,  // 'int8'
-    Int16, // 'int16'
-    Int32, // 'int32'
-    Int64, // 'int64'
+    Int,       // 'int'
+    Int8,      // 'int8'
+    Int16,     // 'int16'
+    Int32,     // 'int32'
+    Int64,     // 'int64'
+    IntN(u16), // 'int' followed by any other width
 
     // Floating-point Types
-    Float,   // 'float'
-    Float8,  // 'float8'
-    Float16, // 'float16'
-    Float32, // 'float32'
-    Float64, // 'float64'
+    Float,       // 'float'
+    Float8,      // 'float8'
+    Float16,     // 'float16'
+    Float32,     // 'float32'
+    Float64,     // 'float64'
+    FloatN(u16), // 'float' followed by any other width
 
     // Logical Types
     Bool, // 'bool'
 
     // Textual Types
-    Char,   // 'char'
-    Char8,  // 'char8'
-    Char16, // 'char16'
-    Char32, // 'char32'
+    Char,       // 'char'
+    Char8,      // 'char8'
+    Char16,     // 'char16'
+    Char32,     // 'char32'
+    CharN(u16), // 'char' followed by any other width
 
     //
     // Punctuators
     //
 
     // Regular Punctuators
-    Apostrophe,      // '''
-    QutationMark,    // '"'
-    FullStop,        // '.'
-    Comma,           // ','
-    Colon,           // ':'
-    Semicolon,       // ';'
-    EqualsSign,      // '='
-    PlusSign,        // '+'
-    MinuxSign,       // '-'
-    Asterisk,        // '*'
-    Solidus,         // '/'
-    ReverseSolidus,  // '\'
-    VerticalLine,    // '|'
-    ExclamationMark, // '!'
-    QuestionMark,    // '?'
-    ComercialAt,     // '@'
-    NumberSign,      // '#'
-
-    RightwardsArrow, // '->'
+    Apostrophe,       // '''
+    QutationMark,     // '"'
+    FullStop,         // '.'
+    Comma,            // ','
+    Colon,            // ':'
+    Semicolon,        // ';'
+    EqualsSign,       // '='
+    PlusSign,         // '+'
+    MinuxSign,        // '-'
+    Asterisk,         // '*'
+    Solidus,          // '/'
+    ReverseSolidus,   // '\'
+    VerticalLine,     // '|'
+    Ampersand,        // '&'
+    ExclamationMark,  // '!'
+    QuestionMark,     // '?'
+    ComercialAt,      // '@'
+    NumberSign,       // '#'
+    PercentSign,      // '%'
+    CircumflexAccent, // '^'
+    Tilde,            // '~'
+    DollarSign,       // '$'
+    Underscore,       // '_', on its own as a wildcard
+
+    RightwardsArrow,       // '->'
+    RightwardsDoubleArrow, // '=>'
+    DoubleEquals,          // '=='
+    NotEquals,             // '!='
+    LessThanOrEqual,       // '<='
+    GreaterThanOrEqual,    // '>='
+    DoubleAmpersand,       // '&&'
+    DoubleVerticalLine,    // '||'
+    DoubleColon,           // '::'
+    PlusEquals,            // '+='
+    MinusEquals,           // '-='
+    AsteriskEquals,        // '*='
+    SolidusEquals,         // '/='
+    DoubleLeftAngle,       // '<<'
+    DoubleRightAngle,      // '>>'
+    PercentEquals,         // '%='
+    CircumflexEquals,      // '^='
+    DoubleFullStop,        // '..'
+    DoubleFullStopEquals,  // '..='
 
     // Delimiterized Punctuators
     LeftCurlyBracket,   // '{'
@@ -205,3 +332,278 @@ pub enum TokenType {
     LeftSquareBracket,  // '['
     RightSquareBracket, // ']'
 }
+
+// The type written straight after a numeric literal, as in `21int8`, with the
+// width left out for the plain `bit`, `int` and `float`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NumericSuffix {
+    Bit(Option<u16>),
+    Int(Option<u16>),
+    Float(Option<u16>),
+}
+
+// A piece of a string literal containing `\(...)` interpolations.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart<'a> {
+    Literal(Cow<'a, str>),
+    // The tokens between the parentheses of an interpolation.
+    Tokens(Vec<Token<'a>>),
+}
+
+impl StringPart<'_> {
+    pub fn into_owned(self) -> StringPart<'static> {
+        match self {
+            StringPart::Literal(text) => StringPart::Literal(Cow::Owned(text.into_owned())),
+            StringPart::Tokens(tokens) => {
+                StringPart::Tokens(tokens.into_iter().map(Token::into_owned).collect())
+            }
+        }
+    }
+}
+
+// The width a character literal is declared with, as in `c16'a'`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CharWidth {
+    Char8,
+    Char16,
+    Char32,
+}
+
+impl TokenType<'_> {
+    // Copies any borrowed text so the type no longer depends on the source.
+    pub fn into_owned(self) -> TokenType<'static> {
+        match self {
+            TokenType::None => TokenType::None,
+            TokenType::EndOfFile => TokenType::EndOfFile,
+            TokenType::Identifier(text) => TokenType::Identifier(Cow::Owned(text.into_owned())),
+            TokenType::MacroIdentifier(text) => {
+                TokenType::MacroIdentifier(Cow::Owned(text.into_owned()))
+            }
+            TokenType::InterpolatedString(parts) => TokenType::InterpolatedString(
+                parts.into_iter().map(StringPart::into_owned).collect(),
+            ),
+            TokenType::DocComment(text) => TokenType::DocComment(Cow::Owned(text.into_owned())),
+            TokenType::Bits { value, suffix } => TokenType::Bits { value, suffix },
+            TokenType::Integer { value, suffix } => TokenType::Integer { value, suffix },
+            TokenType::Decimal { value, suffix } => TokenType::Decimal { value, suffix },
+            TokenType::Boolean(value) => TokenType::Boolean(value),
+            TokenType::String(text) => TokenType::String(Cow::Owned(text.into_owned())),
+            TokenType::Character { value, width } => TokenType::Character { value, width },
+            TokenType::Null => TokenType::Null,
+            TokenType::Module => TokenType::Module,
+            TokenType::Trait => TokenType::Trait,
+            TokenType::Type => TokenType::Type,
+            TokenType::Extend => TokenType::Extend,
+            TokenType::Function => TokenType::Function,
+            TokenType::Value => TokenType::Value,
+            TokenType::Alias => TokenType::Alias,
+            TokenType::Use => TokenType::Use,
+            TokenType::Return => TokenType::Return,
+            TokenType::As => TokenType::As,
+            TokenType::If => TokenType::If,
+            TokenType::Else => TokenType::Else,
+            TokenType::Match => TokenType::Match,
+            TokenType::While => TokenType::While,
+            TokenType::For => TokenType::For,
+            TokenType::In => TokenType::In,
+            TokenType::Loop => TokenType::Loop,
+            TokenType::Break => TokenType::Break,
+            TokenType::Continue => TokenType::Continue,
+            TokenType::Public => TokenType::Public,
+            TokenType::Private => TokenType::Private,
+            TokenType::Mutable => TokenType::Mutable,
+            TokenType::Constant => TokenType::Constant,
+            TokenType::LowerSelf => TokenType::LowerSelf,
+            TokenType::UpperSelf => TokenType::UpperSelf,
+            TokenType::True => TokenType::True,
+            TokenType::False => TokenType::False,
+            TokenType::Bit => TokenType::Bit,
+            TokenType::Bit8 => TokenType::Bit8,
+            TokenType::Bit16 => TokenType::Bit16,
+            TokenType::Bit32 => TokenType::Bit32,
+            TokenType::Bit64 => TokenType::Bit64,
+            TokenType::BitN(value) => TokenType::BitN(value),
+            TokenType::Int => TokenType::Int,
+            TokenType::Int8 => TokenType::Int8,
+            TokenType::Int16 => TokenType::Int16,
+            TokenType::Int32 => TokenType::Int32,
+            TokenType::Int64 => TokenType::Int64,
+            TokenType::IntN(value) => TokenType::IntN(value),
+            TokenType::Float => TokenType::Float,
+            TokenType::Float8 => TokenType::Float8,
+            TokenType::Float16 => TokenType::Float16,
+            TokenType::Float32 => TokenType::Float32,
+            TokenType::Float64 => TokenType::Float64,
+            TokenType::FloatN(value) => TokenType::FloatN(value),
+            TokenType::Bool => TokenType::Bool,
+            TokenType::Char => TokenType::Char,
+            TokenType::Char8 => TokenType::Char8,
+            TokenType::Char16 => TokenType::Char16,
+            TokenType::Char32 => TokenType::Char32,
+            TokenType::CharN(value) => TokenType::CharN(value),
+            TokenType::Apostrophe => TokenType::Apostrophe,
+            TokenType::QutationMark => TokenType::QutationMark,
+            TokenType::FullStop => TokenType::FullStop,
+            TokenType::Comma => TokenType::Comma,
+            TokenType::Colon => TokenType::Colon,
+            TokenType::Semicolon => TokenType::Semicolon,
+            TokenType::EqualsSign => TokenType::EqualsSign,
+            TokenType::PlusSign => TokenType::PlusSign,
+            TokenType::MinuxSign => TokenType::MinuxSign,
+            TokenType::Asterisk => TokenType::Asterisk,
+            TokenType::Solidus => TokenType::Solidus,
+            TokenType::ReverseSolidus => TokenType::ReverseSolidus,
+            TokenType::VerticalLine => TokenType::VerticalLine,
+            TokenType::Ampersand => TokenType::Ampersand,
+            TokenType::ExclamationMark => TokenType::ExclamationMark,
+            TokenType::QuestionMark => TokenType::QuestionMark,
+            TokenType::ComercialAt => TokenType::ComercialAt,
+            TokenType::NumberSign => TokenType::NumberSign,
+            TokenType::PercentSign => TokenType::PercentSign,
+            TokenType::CircumflexAccent => TokenType::CircumflexAccent,
+            TokenType::Tilde => TokenType::Tilde,
+            TokenType::DollarSign => TokenType::DollarSign,
+            TokenType::Underscore => TokenType::Underscore,
+            TokenType::RightwardsArrow => TokenType::RightwardsArrow,
+            TokenType::RightwardsDoubleArrow => TokenType::RightwardsDoubleArrow,
+            TokenType::DoubleEquals => TokenType::DoubleEquals,
+            TokenType::NotEquals => TokenType::NotEquals,
+            TokenType::LessThanOrEqual => TokenType::LessThanOrEqual,
+            TokenType::GreaterThanOrEqual => TokenType::GreaterThanOrEqual,
+            TokenType::DoubleAmpersand => TokenType::DoubleAmpersand,
+            TokenType::DoubleVerticalLine => TokenType::DoubleVerticalLine,
+            TokenType::DoubleColon => TokenType::DoubleColon,
+            TokenType::PlusEquals => TokenType::PlusEquals,
+            TokenType::MinusEquals => TokenType::MinusEquals,
+            TokenType::AsteriskEquals => TokenType::AsteriskEquals,
+            TokenType::SolidusEquals => TokenType::SolidusEquals,
+            TokenType::DoubleLeftAngle => TokenType::DoubleLeftAngle,
+            TokenType::DoubleRightAngle => TokenType::DoubleRightAngle,
+            TokenType::PercentEquals => TokenType::PercentEquals,
+            TokenType::CircumflexEquals => TokenType::CircumflexEquals,
+            TokenType::DoubleFullStop => TokenType::DoubleFullStop,
+            TokenType::DoubleFullStopEquals => TokenType::DoubleFullStopEquals,
+            TokenType::LeftCurlyBracket => TokenType::LeftCurlyBracket,
+            TokenType::RightCurlyBracket => TokenType::RightCurlyBracket,
+            TokenType::LeftParenthesis => TokenType::LeftParenthesis,
+            TokenType::RightParenthesis => TokenType::RightParenthesis,
+            TokenType::LeftAngleBracket => TokenType::LeftAngleBracket,
+            TokenType::RightAngleBracket => TokenType::RightAngleBracket,
+            TokenType::LeftSquareBracket => TokenType::LeftSquareBracket,
+            TokenType::RightSquareBracket => TokenType::RightSquareBracket,
+        }
+    }
+
+    // Whether the token can come before an item to modify it, as in
+    // `public function`.
+    pub fn is_item_modifier(&self) -> bool {
+        matches!(self, TokenType::Public | TokenType::Private)
+    }
+
+    // Whether the token says if a value can change, as in `value mutable x`.
+    pub fn is_mutability(&self) -> bool {
+        matches!(self, TokenType::Mutable | TokenType::Constant)
+    }
+
+    // Whether the token names a built-in type, like `bit8` or `Self`.
+    pub fn is_type_word(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Bit
+                | TokenType::Bit8
+                | TokenType::Bit16
+                | TokenType::Bit32
+                | TokenType::Bit64
+                | TokenType::BitN(_)
+                | TokenType::Int
+                | TokenType::Int8
+                | TokenType::Int16
+                | TokenType::Int32
+                | TokenType::Int64
+                | TokenType::IntN(_)
+                | TokenType::Float
+                | TokenType::Float8
+                | TokenType::Float16
+                | TokenType::Float32
+                | TokenType::Float64
+                | TokenType::FloatN(_)
+                | TokenType::Bool
+                | TokenType::Char
+                | TokenType::Char8
+                | TokenType::Char16
+                | TokenType::Char32
+                | TokenType::CharN(_)
+                | TokenType::UpperSelf
+        )
+    }
+
+    // The source spelling of a keyword, looked up in the lexer's keyword table.
+    pub fn keyword_str(&self) -> Option<&'static str> {
+        match self {
+            TokenType::Boolean(true) => Some("true"),
+            TokenType::Boolean(false) => Some("false"),
+            _ => KEYWORDS
+                .entries()
+                .find(|(_, keyword)| *keyword == self)
+                .map(|(spelling, _)| *spelling),
+        }
+    }
+
+    // The source spelling of a punctuator.
+    pub fn punctuator_str(&self) -> Option<&'static str> {
+        let spelling = match self {
+            TokenType::Apostrophe => "'",
+            TokenType::QutationMark => "\"",
+            TokenType::FullStop => ".",
+            TokenType::Comma => ",",
+            TokenType::Colon => ":",
+            TokenType::Semicolon => ";",
+            TokenType::EqualsSign => "=",
+            TokenType::PlusSign => "+",
+            TokenType::MinuxSign => "-",
+            TokenType::Asterisk => "*",
+            TokenType::Solidus => "/",
+            TokenType::ReverseSolidus => "\\",
+            TokenType::VerticalLine => "|",
+            TokenType::Ampersand => "&",
+            TokenType::ExclamationMark => "!",
+            TokenType::QuestionMark => "?",
+            TokenType::ComercialAt => "@",
+            TokenType::NumberSign => "#",
+            TokenType::PercentSign => "%",
+            TokenType::CircumflexAccent => "^",
+            TokenType::Tilde => "~",
+            TokenType::DollarSign => "$",
+            TokenType::Underscore => "_",
+            TokenType::RightwardsArrow => "->",
+            TokenType::RightwardsDoubleArrow => "=>",
+            TokenType::DoubleEquals => "==",
+            TokenType::NotEquals => "!=",
+            TokenType::LessThanOrEqual => "<=",
+            TokenType::GreaterThanOrEqual => ">=",
+            TokenType::DoubleAmpersand => "&&",
+            TokenType::DoubleVerticalLine => "||",
+            TokenType::DoubleColon => "::",
+            TokenType::PlusEquals => "+=",
+            TokenType::MinusEquals => "-=",
+            TokenType::AsteriskEquals => "*=",
+            TokenType::SolidusEquals => "/=",
+            TokenType::DoubleLeftAngle => "<<",
+            TokenType::DoubleRightAngle => ">>",
+            TokenType::PercentEquals => "%=",
+            TokenType::CircumflexEquals => "^=",
+            TokenType::DoubleFullStop => "..",
+            TokenType::DoubleFullStopEquals => "..=",
+            TokenType::LeftCurlyBracket => "{",
+            TokenType::RightCurlyBracket => "}",
+            TokenType::LeftParenthesis => "(",
+            TokenType::RightParenthesis => ")",
+            TokenType::LeftAngleBracket => "<",
+            TokenType::RightAngleBracket => ">",
+            TokenType::LeftSquareBracket => "[",
+            TokenType::RightSquareBracket => "]",
+            _ => return None,
+        };
+        Some(spelling)
+    }
+}